@@ -4,29 +4,381 @@ use color_eyre::{
     eyre::WrapErr, Result
 };
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use ratatui::{
     prelude::*, 
     style::Color, 
     widgets::{block::*, Paragraph, *}
 };
 
-use std::env;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+// terminals repeat a held key roughly every 30ms, anything closer than this is not a new press
+const DEBOUNCE: Duration = Duration::from_millis(60);
+// how long to wait for input when nothing on screen is changing by itself
+const IDLE_POLL: Duration = Duration::from_millis(250);
+// plain borders for terminals that can't draw box characters
+const ASCII_BORDER: symbols::border::Set = symbols::border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-"
+};
+// blocked cells are filled in completely
+const ASCII_BLOCKED: symbols::border::Set = symbols::border::Set {
+    top_left: "X",
+    top_right: "X",
+    bottom_left: "X",
+    bottom_right: "X",
+    vertical_left: "X",
+    vertical_right: "X",
+    horizontal_top: "X",
+    horizontal_bottom: "X"
+};
+const ASCII_HIGHLIGHT: symbols::border::Set = symbols::border::Set {
+    top_left: "#",
+    top_right: "#",
+    bottom_left: "#",
+    bottom_right: "#",
+    vertical_left: "#",
+    vertical_right: "#",
+    horizontal_top: "#",
+    horizontal_bottom: "#"
+};
+// how long the points of the last move show next to the score
+const GAINED: Duration = Duration::from_millis(800);
+// board sizes that can be picked in the menu
+pub const MIN_SIZE: usize = 3;
+pub const MAX_SIZE: usize = 8;
+// how long a status message stays on screen
+const MESSAGE: Duration = Duration::from_millis(1500);
+// how many moves can be undone
+const UNDO_DEPTH: usize = 100;
+// most queued events applied before the board is drawn again
+const MAX_DRAIN: usize = 32;
+// the smallest cell that still fits its border and a four digit tile
+const MIN_CELL_WIDTH: u16 = 6;
+const MIN_CELL_HEIGHT: u16 = 3;
+// columns per row of a cell, terminal characters are about twice as tall as wide so this looks square
+pub const ASPECT: f64 = 2.0;
+// how long a freshly spawned tile stays highlighted, and the arrow of the last move is shown
+const HIGHLIGHT: Duration = Duration::from_millis(300);
+// time between two steps of the score counting up to a new total
+const SCORE_TICK: Duration = Duration::from_millis(30);
+// how long merged tiles are drawn lighter
+const PULSE: Duration = Duration::from_millis(150);
+// tiles that are celebrated the first time they show up in a game
+const MILESTONES: [u64; 3] = [128, 512, 2048];
+// how long a milestone flashes in the title
+const MILESTONE: Duration = Duration::from_millis(2500);
+
+// tiles on a new board unless --start-tiles asks for more or less
+pub const START_TILES: usize = 2;
+
+// moves the side panel keeps, older ones drop off
+const MOVE_PANEL_DEPTH: usize = 200;
+// columns of the side panel, borders included
+const PANEL_WIDTH: u16 = 16;
+
+use crate::challenge::encode_challenge;
+use crate::errors::GameError;
+use crate::feedback::{Feedback, Silent};
 use crate::read_write::*;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct App {
     pub score: u64,
-    pub highscore: u64,
+    // what the status line shows, it counts up to score over a few frames
+    displayed_score: u64,
+    score_ticked: Instant,
+    pub records: Records,
+    mode: GameMode,
+    difficulty: Difficulty,
+    // fields per row and column of the next game
+    size: usize,
+    // rows and columns of a board that is not square, set with --rows and --cols.
+    // it takes the place of size until another size is picked, and keeps no highscore
+    shape: Option<(usize, usize)>,
+    // cells blocked at random in every new game
+    blockers: usize,
+    // tiles a new board starts with, fewer if the blockers leave no room
+    start_tiles: usize,
+    // rows between the board and the edge of the terminal, the sides get twice as many columns
+    margin: u16,
+    // the start screen where the board size is picked
+    menu: bool,
+    // the selected row while the settings screen is open from the menu
+    settings: Option<usize>,
+    // what Preferences.txt holds, without the overrides from the command line
+    prefs: Prefs,
+    // practice games start from a board set up by hand and never count for the highscore
+    practice: bool,
+    // the selected field while a practice board is set up
+    cursor: Option<usize>,
+    // the board new games start from, set up in practice or loaded with --load
+    setup: Option<Grid>,
+    // the tile that wins the game, in the classic sequence
+    target: u64,
+    // how tiles merge in new games
+    rule: Rule,
+    // nothing is written to disk, the highscores of this session are lost on exit
+    no_save: bool,
+    moves: u64,
+    // undos allowed per game, None means unlimited
+    undo_limit: Option<u64>,
+    undos_used: u64,
+    // swaps of two fields allowed per game, s picks the fields with the arrows and Enter
+    swap_limit: u64,
+    swaps_used: u64,
+    // set while a swap is picked, holding the first field once there is one. inspect is the cursor
+    swapping: Option<Option<usize>>,
+    history: Vec<(Grid, u64, u64)>,
+    message: Option<(String, Instant)>,
+    pub log: Option<MoveLog>,
+    // the board is written here after every move, set with --autosave
+    autosave: Option<PathBuf>,
+    // told about merges, spawns and the end of the game
+    feedback: Box<dyn Feedback>,
+    seed: Option<u64>,
+    // the seed of the game being played, drawn at random for every game without --seed, x shares it
+    game_seed: u64,
+    rng: StdRng,
+    // time spent playing, the clock only runs while the game is neither paused nor over
+    played: Duration,
+    resumed: Option<Instant>,
     exit: bool,
     on_pause: bool,
+    // set when the pause came from the terminal losing focus, with auto_resume getting it back ends that pause
+    paused_by_focus: bool,
+    auto_resume: bool,
+    // the field looked at while paused, the arrows move it instead of the tiles
+    inspect: Option<usize>,
     dead: bool,
     grid: Grid,
     won: bool,
-    ignore_win: bool
+    ignore_win: bool,
+    needs_redraw: bool,
+    autorepeat: bool,
+    ascii: bool,
+    // no score on screen and no game over, a full board just stays on screen
+    zen: bool,
+    // tiles show their exponent, 11 instead of 2048, colors still follow the value
+    exponent: bool,
+    // the old game over screen that shows nothing but dead
+    hide_dead_board: bool,
+    // always draw the small board without borders, not just when the terminal is too small
+    mini: bool,
+    // marks the tiles each direction would merge
+    merge_preview: bool,
+    // labels every cell with its index and its neighbours, set with the hidden --debug
+    debug: bool,
+    // the spawn highlight, the merge pulse and the score counter run this many times as fast,
+    // reduced motion turns them off and every change shows at once
+    anim_speed: f64,
+    reduced_motion: bool,
+    // columns per row of every cell, 0 stretches the cells over the whole screen
+    aspect: f64,
+    // most frames drawn per second
+    fps: u32,
+    last_frame: Option<Instant>,
+    // the play time in whole seconds as the status line last showed it
+    clock_secs: u64,
+    // the overlay that explains the tile colors, g opens it and any key closes it
+    legend: bool,
+    // the side panel with the last moves, m toggles it and page up and down scroll it.
+    // every entry is the move number, its direction and the points it gained
+    move_panel: bool,
+    recent_moves: Vec<(u64, Direction, u64)>,
+    panel_scroll: usize,
+    // say so when a key does nothing, toggled with h
+    key_hints: bool,
+    last_key: Option<(KeyCode, Instant)>,
+    drag_start: Option<(u16, u16)>,
+    last_scroll: Option<(Direction, Instant)>,
+    spawned: Option<(usize, Instant)>,
+    pulse: Option<(Vec<usize>, Instant)>,
+    // set while the merge pulse runs. a move pressed meanwhile waits in queued and plays once
+    // the pulse is over, only one waits and further presses are dropped, so mashing keys never
+    // starts a pulse on a board that moved under the one still drawn
+    animating: bool,
+    queued: Option<Direction>,
+    gained: Option<(u64, Instant)>,
+    // the direction of the last move, flashed in the status line
+    last_move: Option<(Direction, Instant)>,
+    // merges of the last move when there was more than one, shown as long as the points gained
+    combo: Option<(usize, Instant)>,
+    // the milestones this game already celebrated, and the one flashing in the title
+    milestones: Vec<Milestone>,
+    milestone: Option<(String, Instant)>,
+    // when the score went past the best, the highscore in the status line lights up for as long as the title says so
+    record: Option<Instant>,
+    // what each field looked like when it was last drawn, rebuilt only when its value changes
+    cells: RefCell<Vec<CachedCell>>
+}
+
+// everything that can be set up before the first frame, main fills it from the command line and
+// Preferences.txt. the defaults play like a game started without any options
+#[derive(Debug)]
+pub struct AppConfig {
+    pub seed: Option<u64>,
+    // the size picked in the menu, and the rows and columns that take its place on a board that is not square
+    pub size: usize,
+    pub shape: Option<(usize, usize)>,
+    pub records: Records,
+    pub prefs: Prefs,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub rule: Rule,
+    pub target: u64,
+    pub blockers: usize,
+    pub start_tiles: usize,
+    pub undo_limit: Option<u64>,
+    pub swap_limit: u64,
+    pub practice: bool,
+    pub zen: bool,
+    pub no_save: bool,
+    pub autorepeat: bool,
+    pub ascii: bool,
+    pub margin: u16,
+    pub fps: u32,
+    pub merge_preview: bool,
+    pub anim_speed: f64,
+    pub reduced_motion: bool,
+    // columns per row of every cell, 0 fills the screen
+    pub aspect: f64,
+    pub mini: bool,
+    pub exponent: bool,
+    pub hide_dead_board: bool,
+    pub debug: bool,
+    // a game paused because the terminal lost focus goes on once it is back
+    pub auto_resume: bool,
+    pub feedback: Box<dyn Feedback>,
+    pub log: Option<MoveLog>,
+    pub autosave: Option<PathBuf>,
+    // a board to start on instead of the menu
+    pub load: Option<Grid>,
+    // a game kept by --autosave to go on with, the mode and difficulty it was played on win
+    pub resume: Option<SavedGame>
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            seed: None,
+            size: 4,
+            shape: None,
+            records: Records::default(),
+            prefs: Prefs::default(),
+            mode: GameMode::Classic,
+            difficulty: Difficulty::Normal,
+            rule: Rule::Classic,
+            target: 2048,
+            blockers: 0,
+            start_tiles: START_TILES,
+            undo_limit: None,
+            swap_limit: 0,
+            practice: false,
+            zen: false,
+            no_save: false,
+            autorepeat: false,
+            ascii: false,
+            margin: 2,
+            fps: 60,
+            merge_preview: false,
+            anim_speed: 1.0,
+            reduced_motion: false,
+            aspect: ASPECT,
+            mini: false,
+            exponent: false,
+            hide_dead_board: false,
+            debug: false,
+            auto_resume: false,
+            feedback: Box::new(Silent),
+            log: None,
+            autosave: None,
+            load: None,
+            resume: None
+        }
+    }
+}
+
+impl AppConfig {
+    // every value is checked here, a bad one would only show as a panic somewhere in the game loop
+    pub fn build(self) -> Result<App> {
+        let mut app = App::new(self.seed, self.size)?;
+        if let Some((rows, cols)) = self.shape {
+            check_dimensions(rows, cols)?;
+        }
+        let (rows, cols) = self.shape.unwrap_or((self.size, self.size));
+        check_start_tiles(self.start_tiles, rows, cols, self.blockers)?;
+        if self.fps == 0 {
+            return Err(GameError::BadArgs("`--fps` must be at least 1".to_string()).into());
+        }
+        if !(0.1..=10.0).contains(&self.anim_speed) {
+            return Err(GameError::BadArgs(format!("`--anim-speed` must be between 0.1 and 10, got {}", self.anim_speed)).into());
+        }
+        if self.aspect != 0.0 && !(0.5..=4.0).contains(&self.aspect) {
+            return Err(GameError::BadArgs(format!("`--aspect` must be 0 or between 0.5 and 4, got {}", self.aspect)).into());
+        }
+        if self.target < 8 || !self.target.is_power_of_two() {
+            return Err(GameError::BadArgs(format!("`--target` must be a power of two of at least 8, got {}", self.target)).into());
+        }
+        app.shape = self.shape;
+        app.records = self.records;
+        app.prefs = self.prefs;
+        app.mode = self.mode;
+        app.difficulty = self.difficulty;
+        app.rule = self.rule;
+        app.target = self.target;
+        app.blockers = self.blockers;
+        app.start_tiles = self.start_tiles;
+        app.undo_limit = self.undo_limit;
+        app.swap_limit = self.swap_limit;
+        app.practice = self.practice;
+        app.zen = self.zen;
+        app.no_save = self.no_save;
+        app.autorepeat = self.autorepeat;
+        app.ascii = self.ascii;
+        app.margin = self.margin;
+        app.fps = self.fps;
+        app.merge_preview = self.merge_preview;
+        app.anim_speed = self.anim_speed;
+        app.reduced_motion = self.reduced_motion;
+        app.aspect = self.aspect;
+        app.mini = self.mini;
+        app.exponent = self.exponent;
+        app.hide_dead_board = self.hide_dead_board;
+        app.debug = self.debug;
+        app.auto_resume = self.auto_resume;
+        app.feedback = self.feedback;
+        app.log = self.log;
+        app.autosave = self.autosave;
+        if let Some(grid) = self.load {
+            app.load(grid)?;
+        }
+        if let Some(saved) = self.resume {
+            app.resume(saved)?;
+        }
+        Ok(app)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct CachedCell {
+    val: Option<u64>,
+    label: String,
+    color: Color
 }
 
 impl Widget for &App {
@@ -34,53 +386,343 @@ impl Widget for &App {
         where
             Self: Sized {
 
-                let instructions = Title::from(Line::from(vec![
+                let instructions = if self.settings.is_some() {
+                    Title::from(Line::from(vec![
+                        " select:".bold(),
+                        " <up/down> ".bold(),
+                        " change:".bold(),
+                        " <left/right> ".bold(),
+                        " back:".bold(),
+                        " <Enter> ".bold()
+                    ]))
+                }
+                else if self.menu {
+                    Title::from(Line::from(vec![
+                        " size:".bold(),
+                        " <left/right> ".bold(),
+                        " play:".bold(),
+                        " <Enter> ".bold(),
+                        " settings:".bold(),
+                        " <s> ".bold(),
+                        " exit:".bold(),
+                        " <q> ".bold()
+                    ]))
+                }
+                else if self.swapping.is_some() {
+                    Title::from(Line::from(vec![
+                        " select:".bold(),
+                        " <arrows> ".bold(),
+                        " pick:".bold(),
+                        " <Enter> ".bold(),
+                        " cancel:".bold(),
+                        " <Esc> ".bold()
+                    ]))
+                }
+                else if self.cursor.is_some() {
+                    Title::from(Line::from(vec![
+                        " select:".bold(),
+                        " <arrows> ".bold(),
+                        " tile:".bold(),
+                        " <+/-> ".bold(),
+                        " clear:".bold(),
+                        " <0> ".bold(),
+                        " play:".bold(),
+                        " <Enter> ".bold(),
+                        " exit:".bold(),
+                        " <q> ".bold()
+                    ]))
+                }
+                else {
+                    Title::from(Line::from(vec![
                     " move:".bold(),
                     " <arrows> ".bold(),
                     " exit:".bold(),
                     " <q> ".bold(),
                     " restart:".bold(),
-                    " <Enter> ".bold()
-                ]));
+                    " <Enter> ".bold(),
+                    " undo:".bold(),
+                    " <u> ".bold(),
+                    " save board:".bold(),
+                    " <e> ".bold(),
+                    " copy:".bold(),
+                    " <y> ".bold()
+                    ]))
+                };
 
-                let block = Block::default()
-                    .borders(Borders::NONE)
-                    .title(Title::from(" 2048 ".bold())
+                let mut title = match self.mode {
+                    GameMode::Classic => " 2048 ".to_string(),
+                    GameMode::Daily => {
+                        let date = self.records.daily_date;
+                        format!(" 2048 | daily {}-{:02}-{:02} ", date / 10000, date / 100 % 100, date % 100)
+                    }
+                    mode => format!(" 2048 | {mode} ")
+                };
+                if self.difficulty != Difficulty::Normal {
+                    title += &format!("| {} ", self.difficulty);
+                }
+                if self.practice {
+                    title += "| practice ";
+                }
+                if self.zen {
+                    title += "| zen ";
+                }
+                if self.rule != Rule::Classic {
+                    title += &format!("| {} ", self.rule);
+                }
+                if self.no_save {
+                    title += "| no save ";
+                }
+                let mut title = vec![title.bold()];
+                if let Some((milestone, _)) = &self.milestone {
+                    title.push(if self.ascii { milestone.as_str().bold() } else { milestone.as_str().bold().yellow() });
+                }
+                // the frame around the whole screen shows what the keys act on
+                let mut block = Block::default()
+                    .borders(Borders::ALL)
+                    .title(Title::from(Line::from(title))
                         .alignment(Alignment::Center)
                         .position(Position::Top))
                     .title(instructions
                         .alignment(Alignment::Center)
-                        .position(Position::Bottom))
-                    .bg(Color::Black);
+                        .position(Position::Bottom));
+                let mut text_style = Style::default();
+                if !self.ascii {
+                    block = block.bg(Color::Black).border_style(Style::default().fg(self.focus_color()));
+                    text_style = text_style.fg(Color::Black);
+                }
+                else {
+                    block = block.border_set(ASCII_BORDER);
+                }
+
+                if let Some(selected) = self.settings {
+                    let settings = self.settings_lines();
+                    let mut lines = vec![Line::from(""); (area.height / 2).saturating_sub(settings.len() as u16 / 2 + 2) as usize];
+                    lines.push(Line::from("settings".bold()));
+                    lines.push(Line::from(""));
+                    for (row, (name, value)) in settings.iter().enumerate() {
+                        let line = format!("{name}:  < {value} >");
+                        lines.push(if row == selected { Line::from(line.bold()) } else { Line::from(line) });
+                    }
+                    Paragraph::new(lines)
+                        .centered()
+                        .block(block)
+                        .render(area, buf);
+                    return;
+                }
+                if self.menu {
+                    let mut lines = vec![Line::from(""); (area.height / 2).saturating_sub(3) as usize];
+                    lines.push(Line::from("new game".bold()));
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(format!("board size:  < {} >", self.board_size())));
+                    if self.shape.is_none() {
+                        lines.push(Line::from(format!("best: {}", self.records.highscore(self.mode, self.size, self.difficulty))));
+                        lines.push(Line::from(format!("best without undo: {}", self.records.hardcore_highscore(self.mode, self.size, self.difficulty))));
+                    }
+                    Paragraph::new(lines)
+                        .centered()
+                        .block(block)
+                        .render(area, buf);
+                    return;
+                }
+
+                // two rows are kept free above and below for the title, the status line and the instructions.
+                // below the minimum the board is drawn as exponents without borders
+                let (rows, cols) = (self.grid.rows as u16, self.grid.cols as u16);
+                let min_width = cols * MIN_CELL_WIDTH + 2;
+                let min_height = 4 + rows * MIN_CELL_HEIGHT;
+                let mini = self.mini || area.width < min_width || area.height < min_height;
+                // too small even for that, resizing redraws the board as soon as it fits again
+                if mini && (area.width < cols * 3 + 2 || area.height < rows + 4) {
+                    let mut lines = vec![Line::from(""); (area.height / 2).saturating_sub(1) as usize];
+                    lines.push(Line::from(format!("Enlarge terminal to at least {}x{}", cols * 3 + 2, rows + 4)));
+                    Paragraph::new(lines)
+                        .centered()
+                        .wrap(Wrap { trim: true })
+                        .block(block)
+                        .render(area, buf);
+                    return;
+                }
 
+                // the move panel takes the right side as long as the board still fits next to it
+                let panel = (self.move_panel && !mini && area.width >= min_width + PANEL_WIDTH && area.height > 4).then(|| Rect {
+                    x: area.x + area.width - 1 - PANEL_WIDTH,
+                    y: area.y + 2,
+                    width: PANEL_WIDTH,
+                    height: area.height - 4
+                });
+                let board_area = Rect { width: area.width - panel.map_or(0, |panel| panel.width), ..area };
+                // the margin gives way before the cells get smaller than the minimum, but never covers the frame
+                let margin = Margin::new(
+                    self.margin.saturating_mul(2).min(board_area.width.saturating_sub(min_width) / 2 + 1),
+                    self.margin.min(area.height.saturating_sub(min_height) / 2 + 2).max(2)
+                );
+                let row_constraints = vec![Constraint::Ratio(1, self.grid.rows as u32); self.grid.rows];
+                let col_constraints = vec![Constraint::Ratio(1, self.grid.cols as u32); self.grid.cols];
                 let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Percentage(25), Constraint::Percentage(25), Constraint::Percentage(25), Constraint::Percentage(25)].as_ref())
-                    .split(area.inner(&Margin::new(32, 2)));
-
-                Paragraph::new(Line::from(self.score.to_string()))
-                    .alignment(Alignment::Left)
-                    .block(block.clone())
-                    .render(area, buf);
-
-                Paragraph::new(Line::from(self.highscore.to_string()))
-                    .alignment(Alignment::Right)
-                    .block(block.clone())
-                    .render(area, buf);
-                
-
-                if !self.dead {
+                    .direction(layout::Direction::Vertical)
+                    .constraints(row_constraints)
+                    .split(self.fit_cells(board_area.inner(&margin)));
+
+                let mut status = self.displayed_score.min(self.score).to_string();
+                if let Some((direction, _)) = self.last_move {
+                    status = format!("{} {status}", direction.arrow(self.ascii));
+                }
+                if let Some((gained, _)) = self.gained {
+                    status += &format!(" +{gained}");
+                }
+                if let Some((combo, _)) = self.combo {
+                    status += &format!(" x{combo} combo!");
+                }
+                if let Some(remaining) = self.countdown() {
+                    let secs = remaining.as_millis().div_ceil(1000);
+                    status += &format!(" | time: {}:{:02}", secs / 60, secs % 60);
+                }
+                else {
+                    let secs = self.play_time().as_secs();
+                    status += &format!(" | {}:{:02}", secs / 60, secs % 60);
+                }
+                if let GameMode::MoveLimit(limit) = self.mode {
+                    status += &format!(" | moves: {}/{limit}", self.moves);
+                }
+                else {
+                    status += &format!(" | moves: {}", self.moves);
+                }
+                if let Some(undos) = self.undos_left() {
+                    status += &format!(" | undos: {undos}");
+                }
+                if self.swap_limit > 0 {
+                    status += &format!(" | swaps: {}", self.swap_limit.saturating_sub(self.swaps_used));
+                }
+                // only merges are left, the next move that merges nothing ends the game
+                let crowded = self.grid.free_fields().is_empty() && self.grid.has_moves();
+                if crowded {
+                    status += " | board full";
+                }
+                block.clone().render(area, buf);
+                // the first row inside the frame, the score on the left and the bests on the right
+                // as long as there is room for both
+                if !self.zen {
+                    let status_bar = Rect {
+                        x: area.x + 1,
+                        y: area.y + 1,
+                        width: area.width - 2,
+                        height: 1
+                    };
+                    let best_tile = format!("best tile: {} | ", self.records.best_tile);
+                    let best = self.best().to_string();
+                    let used = status.chars().count() + best_tile.chars().count() + best.chars().count();
+                    let mut segments = vec![Span::raw(status)];
+                    if let Some(gap) = (status_bar.width as usize).checked_sub(used).filter(|&gap| gap > 0) {
+                        let best = match self.record {
+                            Some(_) if self.ascii => best.reversed(),
+                            Some(_) => best.bold().black().on_yellow(),
+                            None => Span::raw(best)
+                        };
+                        segments.extend([Span::raw(" ".repeat(gap)), Span::raw(best_tile), best]);
+                    }
+                    Paragraph::new(Line::from(segments))
+                        .render(status_bar, buf);
+                }
+
+
+                // a lost board stays visible dimmed behind the banner unless --hide-dead-board
+                let show_board = !self.dead || !self.hide_dead_board;
+                if show_board && mini {
+                    // 1 for 2, 2 for 4 up to 11 for 2048, three columns per field
+                    let lines: Vec<Line> = (0..self.grid.rows)
+                        .map(|i| {
+                            let spans: Vec<Span> = (0..self.grid.cols)
+                                .map(|j| {
+                                    let index = i * self.grid.cols + j;
+                                    let field = self.grid.field(i, j);
+                                    let label = match field.val {
+                                        _ if field.blocked => "#".to_string(),
+                                        0 => ".".to_string(),
+                                        val => self.grid.rule.rank(val).to_string()
+                                    };
+                                    let span = Span::raw(format!("{label:>3}"));
+                                    if self.spawned.is_some_and(|(spawned, _)| spawned == index) || self.cursor == Some(index) || self.inspect == Some(index) || self.swapping == Some(Some(index)) {
+                                        span.reversed()
+                                    }
+                                    else {
+                                        span
+                                    }
+                                })
+                                .collect();
+                            Line::from(spans)
+                        })
+                        .collect();
+                    let board = Rect {
+                        x: area.x + 1,
+                        y: area.y + area.height.saturating_sub(rows) / 2,
+                        width: area.width - 2,
+                        height: rows
+                    };
+                    Paragraph::new(lines)
+                        .centered()
+                        .render(board, buf);
+                }
+                else if show_board {
+                    let previews: Vec<(Direction, Vec<usize>)> = if self.merge_preview {
+                        [Direction::Up, Direction::Right, Direction::Down, Direction::Left]
+                            .into_iter()
+                            .map(|direction| (direction, self.grid.merge_preview(direction)))
+                            .collect()
+                    }
+                    else {
+                        vec![]
+                    };
+                    let mut cells = self.cells.borrow_mut();
+                    if cells.len() != self.grid.fields.len() {
+                        *cells = vec![CachedCell::default(); self.grid.fields.len()];
+                    }
                     for (i, chunk) in chunks.iter().enumerate() {
                         let inner_chunks = Layout::default()
-                            .direction(Direction::Horizontal)
-                            .constraints([Constraint::Percentage(25), Constraint::Percentage(25), Constraint::Percentage(25), Constraint::Percentage(25)].as_ref())
+                            .direction(layout::Direction::Horizontal)
+                            .constraints(col_constraints.clone())
                             .split(*chunk);
     
                         for (j, inner_chunk) in inner_chunks.iter().enumerate() {
-                            let cell_block = Block::default()
-                                .borders(Borders::ALL)
-                                .fg(Color::White)
-                                .bg(self.grid.fields[i * 4 + j].as_ref().unwrap().get_color());
+                            let index = i * self.grid.cols + j;
+                            let val = self.grid.get(i, j);
+                            if cells[index].val != Some(val) {
+                                cells[index] = CachedCell {
+                                    val: Some(val),
+                                    label: if self.exponent && val > 0 { self.grid.rule.rank(val).to_string() } else { val.to_string() },
+                                    color: self.grid.field(i, j).get_color(self.grid.rule)
+                                };
+                            }
+                            let spawned = self.spawned.is_some_and(|(spawned, _)| spawned == index) || self.cursor == Some(index) || self.inspect == Some(index) || self.swapping == Some(Some(index));
+                            let mut cell_block = Block::default()
+                                .borders(Borders::ALL);
+                            if self.grid.field(i, j).blocked {
+                                cell_block = if self.ascii {
+                                    cell_block.border_set(ASCII_BLOCKED)
+                                }
+                                else {
+                                    cell_block.bg(Color::Gray)
+                                };
+                                cell_block.render(*inner_chunk, buf);
+                                self.render_debug(index, *inner_chunk, buf, text_style);
+                                continue;
+                            }
+                            if self.ascii {
+                                cell_block = cell_block.border_set(if spawned { ASCII_HIGHLIGHT } else { ASCII_BORDER });
+                            }
+                            else {
+                                let mut color = cells[index].color;
+                                if self.pulse.as_ref().is_some_and(|(merged, _)| merged.contains(&index)) {
+                                    color = lighter(color);
+                                }
+                                cell_block = cell_block
+                                    .fg(if crowded { Color::Yellow } else { Color::White })
+                                    .bg(color);
+                                if spawned {
+                                    cell_block = cell_block
+                                        .border_type(BorderType::Thick)
+                                        .fg(Color::LightRed);
+                                }
+                            }
     
                             // Render the block
                             cell_block.render(*inner_chunk, buf);
@@ -88,303 +730,1914 @@ impl Widget for &App {
                             // Write the number inside the cell
                             let x = inner_chunk.x + (inner_chunk.width / 2) - 1;
                             let y = inner_chunk.y + (inner_chunk.height / 2);
-                            buf.set_string(x, y, format!("{}", self.grid.fields[i * 4 + j].as_ref().unwrap().val), Style::default().fg(Color::Black));
+                            buf.set_string(x, y, &cells[index].label, text_style);
+
+                            // an arrow for every direction this tile would merge in
+                            let arrows: String = previews
+                                .iter()
+                                .filter(|(_, merging)| merging.contains(&index))
+                                .map(|(direction, _)| direction.arrow(self.ascii))
+                                .collect();
+                            if !arrows.is_empty() && inner_chunk.height > 3 {
+                                let x = inner_chunk.x + (inner_chunk.width / 2) - 1;
+                                buf.set_string(x, inner_chunk.y + 1, arrows, text_style);
+                            }
+                            self.render_debug(index, *inner_chunk, buf, text_style);
                         }   
                     }
                 }
                 else {
-                    Paragraph::new(Line::from(" dead ".bold().red()))
+                    let dead = if self.ascii { " dead ".bold() } else { " dead ".bold().red() };
+                    Paragraph::new(Line::from(dead))
+                        .centered()
+                        .block(block.clone())
+                        .render(area, buf);
+                }
+                // newest first, scrolled back by panel_scroll
+                if let Some(panel) = panel {
+                    let items: Vec<ListItem> = self.recent_moves
+                        .iter()
+                        .rev()
+                        .skip(self.panel_scroll)
+                        .map(|(number, direction, gained)| ListItem::new(format!("{number:>4} {} +{gained}", direction.arrow(self.ascii))))
+                        .collect();
+                    let mut moves = Block::default()
+                        .borders(Borders::ALL)
+                        .title(Title::from(" moves ".bold()).alignment(Alignment::Center));
+                    moves = if self.ascii { moves.border_set(ASCII_BORDER) } else { moves.border_style(Style::default().fg(Color::DarkGray)) };
+                    // List is a stateful widget as well, the plain one needs no scroll state
+                    Widget::render(List::new(items).block(moves), panel, buf);
+                }
+                if self.dead {
+                    if show_board {
+                        buf.set_style(board_area.inner(&margin), Style::default().add_modifier(Modifier::DIM));
+                    }
+                    // the move that reached the target can be the one that leaves no moves, the win comes first
+                    let outcome = if self.won { "won" } else { "dead" };
+                    let result = format!(" {outcome} | score {} | best {} ", self.score, self.best());
+                    // a game lost before the first move has no rate
+                    let per_move = self.score as f64 / self.moves.max(1) as f64;
+                    let per_second = self.score as f64 / self.play_time().as_secs_f64().max(1.0);
+                    let rates = format!(" moves {} | {per_move:.1} per move | {per_second:.1} per second ", self.moves);
+                    let prompt = " Press Enter for a new game, q to quit ";
+                    let lines = if self.ascii {
+                        vec![Line::from(result.bold()), Line::from(rates), Line::from(prompt.bold())]
+                    }
+                    else {
+                        let result = if self.won { result.bold().green().on_black() } else { result.bold().red().on_black() };
+                        vec![Line::from(result), Line::from(rates.on_black()), Line::from(prompt.bold().on_black())]
+                    };
+                    Paragraph::new(lines)
+                        .centered()
+                        .render(Rect { y: area.y + area.height / 2, height: 3, ..area }, buf);
+                }
+
+                if let Some(at) = self.inspect.filter(|_| self.message.is_none()) {
+                    let field = &self.grid.fields[at];
+                    let looked_at = match field.val {
+                        _ if field.blocked => "blocked".to_string(),
+                        0 => "empty".to_string(),
+                        val => val.to_string()
+                    };
+                    let looking = if self.swapping.is_some() { "swap" } else { "paused" };
+                    Paragraph::new(Line::from(format!(" {looking} | {looked_at} ").bold()))
+                        .centered()
+                        .block(block.clone())
+                        .render(area, buf);
+                }
+
+                if let Some((message, _)) = &self.message {
+                    Paragraph::new(Line::from(message.as_str().bold()))
                         .centered()
                         .block(block.clone())
                         .render(area, buf);
                 }
 
                 if self.won {
-                    Paragraph::new(Line::from(vec![Span::from(" Congratulations you won |".bold()), Span::from(" restart: <Enter>, continue: <c>".bold())]))
+                    // there is nothing to go on with on a board without moves
+                    let keys = if self.dead { " restart: <Enter>" } else { " restart: <Enter>, continue: <c>" };
+                    Paragraph::new(Line::from(vec![format!(" Congratulations you reached {} |", self.goal()).bold(), keys.bold()]))
                         .centered()
                         .block(block.clone())
                         .render(area, buf);
                 }
+
+                if self.legend {
+                    // the colors of every tile this board can reach, everything after 2048 shares one
+                    let rule = self.grid.rule;
+                    let mut lines = vec![];
+                    for rank in 1..=(self.grid.fields.len() as u32 + 1).min(12) {
+                        let val = rule.tile(rank);
+                        let color = Field { val, ..Field::new() }.get_color(rule);
+                        let name = if rank == 12 { format!("{val} and up") } else { val.to_string() };
+                        lines.push(Line::from(vec![
+                            Span::raw("    ").bg(color),
+                            Span::raw(format!(" {name:<12}{color:?}"))
+                        ]));
+                    }
+                    let width = 36.min(area.width);
+                    let height = (lines.len() as u16 + 2).min(area.height);
+                    let overlay = Rect {
+                        x: area.x + (area.width - width) / 2,
+                        y: area.y + (area.height - height) / 2,
+                        width,
+                        height
+                    };
+                    Clear.render(overlay, buf);
+                    let mut legend = Block::default().borders(Borders::ALL);
+                    if !self.ascii {
+                        legend = legend.border_style(Style::default().fg(Color::LightCyan));
+                    }
+                    Paragraph::new(lines)
+                        .block(legend
+                            .title(Title::from(" tiles ".bold()).alignment(Alignment::Center))
+                            .title(Title::from(" any key closes ").alignment(Alignment::Center).position(Position::Bottom)))
+                        .render(overlay, buf);
+                }
     }   
 }
 
 impl App {
 
     pub fn run(&mut self, terminal: &mut tui::Tui) -> Result<()> {
+        self.needs_redraw = true;
         loop {
-            terminal.draw(|frame| self.render_frame(frame))?;
-            self.handle_events().wrap_err("handle events failed")?;
+            self.expire_effects();
+            self.play_queued()?;
+            self.advance_score();
+            self.check_time()?;
+            // input is handled as soon as it arrives, only drawing is held to the frame rate
+            if self.needs_redraw && self.frame_wait().is_zero() {
+                terminal.draw(|frame| self.render_frame(frame))?;
+                self.needs_redraw = false;
+                self.last_frame = Some(Instant::now());
+            }
+            // nothing changes between events, so wait for one instead of spinning.
+            // held keys arrive as a burst of events within this poll, with --autorepeat
+            // every one of them is a move, otherwise handle_events debounces them
+            if event::poll(self.poll_timeout())? {
+                self.drain_events().wrap_err("handle events failed")?;
+            }
             if self.exit {
                 break;
             } 
-            if self.on_pause || self.dead {
-                continue;
+        }
+        Ok(())
+    }
+
+    // transient effects have to be cleared by the loop itself, no event triggers that redraw
+    fn expire_effects(&mut self) {
+        if self.spawned.is_some_and(|(_, at)| at.elapsed() >= self.animation(HIGHLIGHT)) {
+            self.spawned = None;
+            self.needs_redraw = true;
+        }
+        if self.pulse.as_ref().is_some_and(|(_, at)| at.elapsed() >= self.animation(PULSE)) {
+            self.pulse = None;
+            self.animating = false;
+            self.needs_redraw = true;
+        }
+        if self.message.as_ref().is_some_and(|(_, at)| at.elapsed() >= MESSAGE) {
+            self.message = None;
+            self.needs_redraw = true;
+        }
+        if self.gained.is_some_and(|(_, at)| at.elapsed() >= GAINED) {
+            self.gained = None;
+            self.needs_redraw = true;
+        }
+        if self.combo.is_some_and(|(_, at)| at.elapsed() >= GAINED) {
+            self.combo = None;
+            self.needs_redraw = true;
+        }
+        if self.last_move.is_some_and(|(_, at)| at.elapsed() >= self.animation(HIGHLIGHT)) {
+            self.last_move = None;
+            self.needs_redraw = true;
+        }
+        if self.milestone.as_ref().is_some_and(|(_, at)| at.elapsed() >= self.animation(MILESTONE)) {
+            self.milestone = None;
+            self.needs_redraw = true;
+        }
+        if self.record.is_some_and(|at| at.elapsed() >= self.animation(MILESTONE)) {
+            self.record = None;
+            self.needs_redraw = true;
+        }
+    }
+
+    fn play_queued(&mut self) -> Result<()> {
+        if !self.animating {
+            if let Some(direction) = self.queued.take() {
+                self.process_move(direction)?;
+            }
+        }
+        Ok(())
+    }
+
+    // closes a quarter of the gap per tick so big merges count up fast and small ones still show.
+    // undo and restart lower the score, the counter follows those at once, and every change with reduced motion
+    fn advance_score(&mut self) {
+        if self.displayed_score > self.score || (self.reduced_motion && self.displayed_score != self.score) {
+            self.displayed_score = self.score;
+            self.needs_redraw = true;
+        }
+        if self.displayed_score < self.score && self.score_ticked.elapsed() >= self.animation(SCORE_TICK) {
+            self.displayed_score += ((self.score - self.displayed_score) / 4).max(1);
+            self.score_ticked = Instant::now();
+            self.needs_redraw = true;
+        }
+    }
+
+    fn play_time(&self) -> Duration {
+        self.played + self.resumed.map_or(Duration::ZERO, |at| at.elapsed())
+    }
+
+    fn stop_clock(&mut self) {
+        if let Some(at) = self.resumed.take() {
+            self.played += at.elapsed();
+        }
+    }
+
+    fn start_clock(&mut self) {
+        if self.resumed.is_none() {
+            self.resumed = Some(Instant::now());
+        }
+    }
+
+    // time left in time attack, None in every other mode
+    fn countdown(&self) -> Option<Duration> {
+        match self.mode {
+            GameMode::TimeAttack(secs) => Some(Duration::from_secs(secs).saturating_sub(self.play_time())),
+            _ => None
+        }
+    }
+
+    fn check_time(&mut self) -> Result<()> {
+        if self.resumed.is_none() {
+            return Ok(());
+        }
+        match self.countdown() {
+            Some(remaining) if remaining.is_zero() => {
+                self.is_dead()?;
+                self.needs_redraw = true;
             }
-            self.highscore();
-            if self.ignore_win {
-                self.reset_max();
+            // the clock in the status line changes every second
+            Some(_) => self.needs_redraw = true,
+            // the played time only needs a frame when its second changes
+            None if !self.zen && self.play_time().as_secs() != self.clock_secs => {
+                self.clock_secs = self.play_time().as_secs();
+                self.needs_redraw = true;
             }
+            None => {}
         }
         Ok(())
     }
 
+    // time until the next frame may be drawn
+    fn frame_wait(&self) -> Duration {
+        let budget = Duration::from_secs(1) / self.fps;
+        self.last_frame.map_or(Duration::ZERO, |at| budget.saturating_sub(at.elapsed()))
+    }
+
+    // wake up in time to clear the next transient effect
+    fn poll_timeout(&self) -> Duration {
+        let mut timeout = IDLE_POLL;
+        if self.needs_redraw {
+            timeout = timeout.min(self.frame_wait());
+        }
+        if self.resumed.is_some() && !self.zen {
+            // the next whole second, that is when the clock shows a new value
+            let to_next_second = match self.countdown() {
+                Some(remaining) => remaining.subsec_nanos(),
+                None => 1_000_000_000 - self.play_time().subsec_nanos()
+            };
+            timeout = timeout.min(Duration::from_nanos(if to_next_second == 0 { 1_000_000_000 } else { to_next_second as u64 }));
+        }
+        if let Some((_, at)) = self.spawned {
+            timeout = timeout.min(self.animation(HIGHLIGHT).saturating_sub(at.elapsed()));
+        }
+        if let Some((_, at)) = &self.pulse {
+            timeout = timeout.min(self.animation(PULSE).saturating_sub(at.elapsed()));
+        }
+        if let Some((_, at)) = &self.message {
+            timeout = timeout.min(MESSAGE.saturating_sub(at.elapsed()));
+        }
+        if let Some((_, at)) = self.gained {
+            timeout = timeout.min(GAINED.saturating_sub(at.elapsed()));
+        }
+        if let Some((_, at)) = self.combo {
+            timeout = timeout.min(GAINED.saturating_sub(at.elapsed()));
+        }
+        if let Some((_, at)) = self.last_move {
+            timeout = timeout.min(self.animation(HIGHLIGHT).saturating_sub(at.elapsed()));
+        }
+        if let Some((_, at)) = &self.milestone {
+            timeout = timeout.min(self.animation(MILESTONE).saturating_sub(at.elapsed()));
+        }
+        if let Some(at) = self.record {
+            timeout = timeout.min(self.animation(MILESTONE).saturating_sub(at.elapsed()));
+        }
+        if self.displayed_score != self.score {
+            timeout = timeout.min(self.animation(SCORE_TICK).saturating_sub(self.score_ticked.elapsed()));
+        }
+        timeout
+    }
+
+    // the part of space the board takes when its cells keep the aspect ratio, centered.
+    // an aspect of 0 stretches the cells over all of it, and they never get smaller than the minimum
+    fn fit_cells(&self, space: Rect) -> Rect {
+        if self.aspect == 0.0 {
+            return space;
+        }
+        let (rows, cols) = (self.grid.rows as u16, self.grid.cols as u16);
+        let height = (space.height / rows).min((space.width as f64 / (cols as f64 * self.aspect)) as u16);
+        let height = height.max(MIN_CELL_HEIGHT);
+        let width = ((height as f64 * self.aspect) as u16).clamp(MIN_CELL_WIDTH, (space.width / cols).max(MIN_CELL_WIDTH));
+        let (width, height) = ((width * cols).min(space.width), (height * rows).min(space.height));
+        Rect {
+            x: space.x + (space.width - width) / 2,
+            y: space.y + (space.height - height) / 2,
+            width,
+            height
+        }
+    }
+
+    // how long an animation lasts at the chosen speed
+    fn animation(&self, duration: Duration) -> Duration {
+        duration.div_f64(self.anim_speed)
+    }
+
+    // the legend draws its own outline, the frame then steps back
+    fn focus_color(&self) -> Color {
+        if self.legend {
+            Color::DarkGray
+        }
+        else if self.settings.is_some() {
+            Color::Yellow
+        }
+        else if self.menu {
+            Color::LightCyan
+        }
+        else if self.cursor.is_some() {
+            Color::LightGreen
+        }
+        else if self.swapping.is_some() {
+            Color::LightYellow
+        }
+        else if self.dead && self.won {
+            Color::LightGreen
+        }
+        else if self.dead {
+            Color::Red
+        }
+        else if self.on_pause {
+            Color::Magenta
+        }
+        else {
+            Color::White
+        }
+    }
+
+    // the index in the top left corner of the cell and the neighbours top right bottom left along
+    // the bottom, - where there is none. cut off where the cell is too small
+    fn render_debug(&self, index: usize, cell: Rect, buf: &mut Buffer, style: Style) {
+        if !self.debug || cell.width < 3 || cell.height < 3 {
+            return;
+        }
+        let width = cell.width as usize - 2;
+        buf.set_stringn(cell.x + 1, cell.y + 1, index.to_string(), width, style);
+        if cell.height > 3 {
+            let neighbours: Vec<String> = self.grid.fields[index].neighbours
+                .iter()
+                .map(|neighbour| neighbour.map_or("-".to_string(), |i| i.to_string()))
+                .collect();
+            buf.set_stringn(cell.x + 1, cell.y + cell.height - 2, neighbours.join(" "), width, style);
+        }
+    }
+
     fn render_frame(&self, frame: &mut Frame) {
         frame.render_widget(self, frame.size());
     }
 
     fn highscore(&mut self) {
-        if self.score > self.highscore {
-            self.highscore = self.score;
+        // a board set up by hand could start arbitrarily close to any score, other rules score differently
+        if self.practice || self.setup.is_some() || self.zen || self.rule != Rule::Classic {
+            return;
+        }
+        let Some(size) = self.grid.square() else {
+            return;
+        };
+        let highscore = self.records.highscores.entry((self.mode, size, self.difficulty)).or_insert(0);
+        if self.score > *highscore {
+            *highscore = self.score;
+        }
+        // a single undo or swap is enough to keep the game off the hardcore board
+        if self.undos_used == 0 && self.swaps_used == 0 {
+            let highscore = self.records.hardcore.entry((self.mode, size, self.difficulty)).or_insert(0);
+            if self.score > *highscore {
+                *highscore = self.score;
+            }
+        }
+        let max_tile = self.grid.max_tile();
+        if max_tile > self.records.best_tile {
+            self.records.best_tile = max_tile;
+        }
+    }
+
+    // applies everything that queued up while the last frame was drawn, in order
+    fn drain_events(&mut self) -> Result<()> {
+        self.handle_events()?;
+        let mut handled = 1;
+        while handled < MAX_DRAIN && !self.exit && event::poll(Duration::ZERO)? {
+            self.handle_events()?;
+            handled += 1;
         }
+        Ok(())
     }
 
     fn handle_events(&mut self) -> Result<()> {
         match event::read()? {
-            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+            // raw mode delivers ctrl-c as a key instead of a SIGINT, it quits like q so the
+            // highscore is saved and the terminal restored, wherever the game is
+            Event::Key(key_event) if key_event.code == KeyCode::Char('c') && key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.exit();
+                Ok(())
+            }
+            Event::Key(key_event) if self.accept_key(&key_event) => {
                 self.handle_key_event(key_event).wrap_err_with(|| {
                     format!("handling key event failed: \n{key_event:#?}")
                 })
             }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
+            Event::FocusLost => self.focus_lost(),
+            Event::FocusGained => self.focus_gained(),
+            Event::Resize(_, _) => {
+                self.needs_redraw = true;
+                Ok(())
+            }
            _ => Ok(())
         }
     }
 
-    pub fn new() -> Result<Self> {
+    // repeats only count as moves with --autorepeat, otherwise one physical press is one move
+    fn accept_key(&mut self, key_event: &KeyEvent) -> bool {
+        match key_event.kind {
+            KeyEventKind::Press if self.autorepeat => true,
+            KeyEventKind::Press => {
+                let now = Instant::now();
+                let repeated = matches!(self.last_key, Some((last, at)) if last == key_event.code && now - at < DEBOUNCE);
+                self.last_key = Some((key_event.code, now));
+                !repeated
+            }
+            KeyEventKind::Repeat => self.autorepeat,
+            KeyEventKind::Release => false
+        }
+    }
+
+    // the same seed always produces the same sequence of tiles.
+    // the board is checked here so nothing after has to handle one without fields
+    pub fn new(seed: Option<u64>, size: usize) -> Result<Self> {
+        check_dimensions(size, size)?;
+        let mut rng = new_rng(seed);
         let app = App {
             score: 0,
-            highscore: 0,
+            displayed_score: 0,
+            score_ticked: Instant::now(),
+            records: Records::default(),
+            mode: GameMode::Classic,
+            difficulty: Difficulty::Normal,
+            size,
+            shape: None,
+            blockers: 0,
+            start_tiles: START_TILES,
+            margin: 2,
+            menu: true,
+            settings: None,
+            prefs: Prefs::default(),
+            practice: false,
+            cursor: None,
+            setup: None,
+            target: 2048,
+            rule: Rule::Classic,
+            no_save: false,
+            moves: 0,
+            undo_limit: None,
+            undos_used: 0,
+            swap_limit: 0,
+            swaps_used: 0,
+            swapping: None,
+            history: vec![],
+            message: None,
+            log: None,
+            autosave: None,
+            feedback: Box::new(Silent),
+            seed,
+            game_seed: seed.unwrap_or_default(),
+            grid: Grid::new(size, size, 0, START_TILES, &mut rng),
+            // the clock starts with the first game, not in the menu
+            played: Duration::ZERO,
+            resumed: None,
+            rng,
             exit: false,
             dead: false,
             on_pause: false,
-            grid: Grid::new(),
+            paused_by_focus: false,
+            auto_resume: false,
+            inspect: None,
             won: false,
-            ignore_win: false
+            ignore_win: false,
+            needs_redraw: true,
+            autorepeat: false,
+            ascii: false,
+            zen: false,
+            key_hints: true,
+            legend: false,
+            move_panel: false,
+            recent_moves: vec![],
+            panel_scroll: 0,
+            merge_preview: false,
+            debug: false,
+            anim_speed: 1.0,
+            reduced_motion: false,
+            aspect: ASPECT,
+            mini: false,
+            hide_dead_board: false,
+            exponent: false,
+            fps: 60,
+            last_frame: None,
+            clock_secs: 0,
+            last_key: None,
+            drag_start: None,
+            last_scroll: None,
+            spawned: None,
+            pulse: None,
+            animating: false,
+            queued: None,
+            gained: None,
+            combo: None,
+            milestones: vec![],
+            milestone: None,
+            record: None,
+            last_move: None,
+            cells: RefCell::new(vec![])
         };
         Ok(app)
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) -> Result<()> {
+        if self.legend {
+            self.legend = false;
+            self.needs_redraw = true;
+            return Ok(());
+        }
+        if self.settings.is_some() {
+            return self.handle_settings_key(key_event);
+        }
+        if self.menu {
+            return self.handle_menu_key(key_event);
+        }
+        if self.cursor.is_some() {
+            return self.handle_setup_key(key_event);
+        }
+        if self.swapping.is_some() {
+            return self.handle_swap_key(key_event);
+        }
         match key_event.code {
             KeyCode::Char('q') => self.exit(),
             KeyCode::Esc => self.pause()?,
             KeyCode::Enter => self.restart()?,
+            KeyCode::Up | KeyCode::Right | KeyCode::Down | KeyCode::Left if self.on_pause => self.move_inspect(key_event.code),
             KeyCode::Right => self.move_right()?,
             KeyCode::Left => self.move_left()?,
             KeyCode::Up => self.move_up()?,
             KeyCode::Down => self.move_down()?,
-            KeyCode::Char('c') => self.ignore_win = true,
+            KeyCode::Char('c') => self.continue_game(),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('s') => self.start_swap(),
+            KeyCode::Char('e') => self.export_board(),
+            KeyCode::Char('y') => self.copy_board(),
+            KeyCode::Char('x') => self.share_challenge(),
+            KeyCode::Char('m') => {
+                self.move_panel = !self.move_panel;
+                self.needs_redraw = true;
+            }
+            KeyCode::PageUp | KeyCode::PageDown if self.move_panel => {
+                self.panel_scroll = if key_event.code == KeyCode::PageUp {
+                    self.panel_scroll.saturating_sub(1)
+                }
+                else {
+                    (self.panel_scroll + 1).min(self.recent_moves.len().saturating_sub(1))
+                };
+                self.needs_redraw = true;
+            }
+            KeyCode::Char('g') => {
+                self.legend = true;
+                self.needs_redraw = true;
+            }
+            KeyCode::Char('h') => {
+                self.key_hints = !self.key_hints;
+                self.show_message(if self.key_hints { "key hints on" } else { "key hints off" });
+            }
+            code if self.key_hints => {
+                let key = match code {
+                    KeyCode::Char(c) => c.to_string(),
+                    code => format!("{code:?}")
+                };
+                self.show_message(&format!("{key} does nothing, h hides this"));
+            }
             _ => {}
         }
-        self.check_for_win();
         Ok(())
     }
 
-    fn restart(&mut self) -> Result<()> {
-
-        if self.dead {
-            let path_to_self = env::current_exe()?;
-            let path = path_to_self
-                .parent()
-                .and_then(|p| p.parent())
-                .and_then(|p|p.parent())
-                .map(|p|p.join("Highscore.bin"))
-                .unwrap();
-            save(&path, self.highscore)?;
-            
-            let num = read(&path)?;
-
-            self.highscore = num;
-            self.score = 0;
-            self.on_pause = false;
-            self.dead = false;
-            self.grid = Grid::new();
+    fn handle_menu_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        match key_event.code {
+            KeyCode::Char('q') => self.exit(),
+            KeyCode::Char('s') => self.settings = Some(0),
+            // the first arrow goes back from --rows and --cols to a square board
+            KeyCode::Left | KeyCode::Right if self.shape.is_some() => self.shape = None,
+            KeyCode::Left if self.size > MIN_SIZE => self.size -= 1,
+            KeyCode::Right if self.size < MAX_SIZE => self.size += 1,
+            KeyCode::Enter => {
+                self.menu = false;
+                self.records.last_size = self.size as u64;
+                self.new_game()?;
+            }
+            _ => return Ok(())
         }
-
+        self.needs_redraw = true;
         Ok(())
     }
 
-    fn exit(&mut self) {
-        self.exit = true;
+    // what the settings screen lists, in order
+    fn settings_lines(&self) -> Vec<(&str, String)> {
+        let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+        vec![
+            ("board size", self.board_size()),
+            ("difficulty", self.difficulty.to_string()),
+            ("ascii", on_off(self.ascii)),
+            ("autorepeat", on_off(self.autorepeat)),
+            ("merge preview", on_off(self.merge_preview))
+        ]
     }
 
-    fn pause(&mut self) -> Result<()> {
-        if self.on_pause {
-            self.on_pause = false;
-        }
-        else {
-            self.on_pause = true;
+    fn handle_settings_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        let selected = self.settings.unwrap();
+        match key_event.code {
+            KeyCode::Char('q') => self.exit(),
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('s') => self.settings = None,
+            KeyCode::Up => self.settings = Some(selected.saturating_sub(1)),
+            KeyCode::Down => self.settings = Some((selected + 1).min(self.settings_lines().len() - 1)),
+            KeyCode::Left | KeyCode::Right => self.change_setting(selected, key_event.code == KeyCode::Right)?,
+            _ => return Ok(())
         }
+        self.needs_redraw = true;
         Ok(())
     }
 
-    fn is_dead(&mut self) -> Result<()> {
-        if !self.dead {
-            self.dead = true;
+    // applies to the next game and is written to the preferences right away
+    fn change_setting(&mut self, row: usize, forward: bool) -> Result<()> {
+        match row {
+            0 => {
+                if self.shape.take().is_none() {
+                    self.size = if forward { (self.size + 1).min(MAX_SIZE) } else { (self.size - 1).max(MIN_SIZE) };
+                }
+                self.prefs.size = Some(self.size);
+            }
+            1 => {
+                self.difficulty = self.difficulty.step(forward);
+                self.prefs.difficulty = self.difficulty;
+            }
+            2 => {
+                self.ascii = !self.ascii;
+                self.prefs.ascii = self.ascii;
+            }
+            3 => {
+                self.autorepeat = !self.autorepeat;
+                self.prefs.autorepeat = self.autorepeat;
+            }
+            _ => {
+                self.merge_preview = !self.merge_preview;
+                self.prefs.merge_preview = self.merge_preview;
+            }
+        }
+        if !self.no_save {
+            save_prefs(&prefs_path()?, &self.prefs).wrap_err("could not save the preferences")?;
         }
         Ok(())
     }
 
-    fn move_left(&mut self) -> Result<()>{
-        self.grid.move_vals(3, &mut self.score)?;
-        self.new_pieces()?;
-        Ok(())
-    }
-
-    fn move_right(&mut self) -> Result<()> {
-        self.grid.move_vals(1, &mut self.score)?;
-        self.new_pieces()?;
-        Ok(())
-    }
-
-    fn move_down(&mut self) -> Result<()> {
-        self.grid.move_vals(2, &mut self.score)?;
-        self.new_pieces()?;
+    // arrows pick a field, + and - double and halve its tile
+    fn handle_setup_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        let field = &mut self.grid.fields[self.cursor.unwrap()];
+        match key_event.code {
+            KeyCode::Char('q') => self.exit(),
+            KeyCode::Enter => self.start_practice()?,
+            KeyCode::Char('e') => self.export_board(),
+            code @ (KeyCode::Up | KeyCode::Right | KeyCode::Down | KeyCode::Left) => {
+                if let Some(next) = Direction::from_arrow(code).and_then(|direction| field.neighbours[direction.index()]) {
+                    self.cursor = Some(next);
+                }
+            }
+            // 2^63 is the largest tile a u64 holds and --load accepts, + stops there
+            KeyCode::Char('+') | KeyCode::Char('=') if !field.blocked => field.val = field.val.checked_mul(2).unwrap_or(field.val).max(2),
+            KeyCode::Char('-') => field.val = if field.val > 2 { field.val / 2 } else { 0 },
+            KeyCode::Char('0') | KeyCode::Backspace | KeyCode::Delete => field.val = 0,
+            _ => return Ok(())
+        }
+        self.needs_redraw = true;
         Ok(())
     }
 
-    fn move_up(&mut self) -> Result<()> {
-        self.grid.move_vals(0, &mut self.score)?;
-        self.new_pieces()?;
+    fn start_practice(&mut self) -> Result<()> {
+        Grid::init_grid(&mut self.grid, self.start_tiles, &mut RandomSpawn { rng: &mut self.rng, difficulty: self.difficulty, rule: self.rule });
+        self.setup = Some(self.grid.clone());
+        self.cursor = None;
+        self.milestones = self.reached_milestones();
+        self.start_clock();
+        self.check_for_win();
+        if !self.grid.has_moves() {
+            self.is_dead()?;
+        }
+        self.needs_redraw = true;
         Ok(())
     }
 
-    fn new_pieces(&mut self) -> Result<()> {
-        let mut rng = thread_rng();
-        let all_full = self.grid.fields.iter().all(|field| field.as_ref().unwrap().val != 0);
-        loop {
-            for field in self.grid.fields.iter_mut() {
-                let rand = rng.gen_range(0.0..1.0);
-                if field.as_ref().unwrap().val == 0 && rand < 1.0 / 16.0 {
-                    let rand_2 = rng.gen_range(0.0..1.0);
-                    if rand_2 < 0.15 {
-                        field.as_mut().unwrap().val = 2;
-                        return  Ok(());
-                    }
-                    else {
-                        field.as_mut().unwrap().val = 2;
-                        return Ok(());
-                    }
+    // a left click drag or a scroll is read as a swipe in that direction
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) -> Result<()> {
+        let direction = match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.drag_start = Some((mouse_event.column, mouse_event.row));
+                None
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.drag_start.take().and_then(|(column, row)| {
+                let dx = mouse_event.column as i32 - column as i32;
+                // cells are about twice as high as they are wide
+                let dy = (mouse_event.row as i32 - row as i32) * 2;
+                if dx.abs().max(dy.abs()) < 2 {
+                    None
                 }
-                if rand < 0.1 && all_full {
-                    self.is_dead()?;
-                    return Ok(());
+                else if dx.abs() > dy.abs() {
+                    Some(if dx > 0 { Direction::Right } else { Direction::Left })
                 }
-            }
+                else {
+                    Some(if dy > 0 { Direction::Down } else { Direction::Up })
+                }
+            }),
+            MouseEventKind::ScrollUp => self.scroll(Direction::Up),
+            MouseEventKind::ScrollDown => self.scroll(Direction::Down),
+            MouseEventKind::ScrollLeft => self.scroll(Direction::Left),
+            MouseEventKind::ScrollRight => self.scroll(Direction::Right),
+            _ => None
+        };
+        match direction {
+            Some(direction) => self.process_move(direction),
+            None => Ok(())
         }
     }
 
-    fn check_for_win(&mut self){
+    // a trackpad sends a burst of scroll events per gesture, only the first one moves
+    fn scroll(&mut self, direction: Direction) -> Option<Direction> {
+        let now = Instant::now();
+        let repeated = matches!(self.last_scroll, Some((last, at)) if last == direction && now - at < DEBOUNCE);
+        self.last_scroll = Some((direction, now));
+        if repeated {
+            None
+        }
+        else {
+            Some(direction)
+        }
+    }
+
+    fn restart(&mut self) -> Result<()> {
+
+        if self.dead {
+            if !self.no_save {
+                save_records(&save_path()?, &self.records)?;
+            }
+            self.new_game()?;
+        }
+
+        Ok(())
+    }
+
+    // goes on with a game --autosave kept as that same game, it counts for the highscores like it did
+    // before. the rng starts afresh, the state it was in is not saved
+    pub fn resume(&mut self, saved: SavedGame) -> Result<()> {
+        let mut grid = Grid::from_values(&saved.board)?;
+        check_dimensions(grid.rows, grid.cols)?;
+        for &i in &saved.blocked {
+            grid.block(i)?;
+        }
+        match grid.square() {
+            Some(size) => {
+                self.size = size;
+                self.shape = None;
+            }
+            None => self.shape = Some((grid.rows, grid.cols))
+        }
+        self.mode = saved.mode;
+        self.difficulty = saved.difficulty;
+        self.zen = saved.zen;
+        self.practice = false;
+        self.setup = None;
+        self.menu = false;
+        self.new_game()?;
+        grid.rule = self.rule;
+        self.grid = grid;
+        if saved.set_up {
+            self.setup = Some(self.grid.clone());
+        }
+        self.score = saved.score;
+        self.displayed_score = saved.score;
+        self.moves = saved.moves;
+        self.undos_used = saved.undos_used;
+        self.swaps_used = saved.swaps_used;
+        self.played = Duration::from_secs(saved.played_secs);
+        self.game_seed = saved.seed;
+        self.ignore_win = saved.continued;
+        self.milestones = self.reached_milestones();
+        self.check_for_win();
+        if !self.grid.has_moves() || matches!(self.mode, GameMode::MoveLimit(limit) if self.moves >= limit) {
+            self.is_dead()?;
+        }
+        Ok(())
+    }
+
+    // skips the menu and plays from a given board, every restart goes back to it
+    pub fn load(&mut self, grid: Grid) -> Result<()> {
+        if let Some(size) = grid.square() {
+            self.size = size;
+        }
+        else {
+            self.shape = Some((grid.rows, grid.cols));
+        }
+        self.setup = Some(grid);
+        self.menu = false;
+        self.new_game()
+    }
+
+    fn new_game(&mut self) -> Result<()> {
+        self.score = 0;
+        self.moves = 0;
+        self.history.clear();
+        self.recent_moves.clear();
+        self.panel_scroll = 0;
+        self.undos_used = 0;
+        self.swaps_used = 0;
+        self.swapping = None;
+        self.on_pause = false;
+        self.inspect = None;
+        self.queued = None;
+        self.dead = false;
+        self.won = false;
+        self.ignore_win = false;
+        self.game_seed = self.seed.unwrap_or_else(rand::random);
+        self.rng = StdRng::seed_from_u64(self.game_seed);
+        let (rows, cols) = self.dimensions();
+        self.grid = Grid::new(rows, cols, self.blockers, self.start_tiles, &mut self.rng);
+        self.played = Duration::ZERO;
+        self.resumed = Some(Instant::now());
+        self.needs_redraw = true;
+        if let Some(setup) = self.setup.as_ref().filter(|setup| (setup.rows, setup.cols) == (rows, cols)) {
+            self.grid = setup.clone();
+        }
+        self.grid.rule = self.rule;
+        self.milestones = self.reached_milestones();
+        self.milestone = None;
+        self.record = None;
+        if self.practice {
+            // the clock starts once the board is set up
+            self.resumed = None;
+            self.cursor = Some(0);
+        }
+        else if self.setup.is_some() {
+            // a loaded board can already be won or lost
+            self.check_for_win();
+            if !self.grid.has_moves() {
+                self.is_dead()?;
+            }
+        }
+        Ok(())
+    }
+
+    // rows and columns of the next game
+    fn dimensions(&self) -> (usize, usize) {
+        self.shape.unwrap_or((self.size, self.size))
+    }
+
+    // columns first, like the size of a terminal
+    fn board_size(&self) -> String {
+        let (rows, cols) = self.dimensions();
+        format!("{cols}x{rows}")
+    }
+
+    // the highscore of the board being played, boards that are not square and other rules have none
+    fn best(&self) -> u64 {
+        if self.grid.rule != Rule::Classic {
+            return 0;
+        }
+        self.grid.square().map_or(0, |size| self.records.highscore(self.mode, size, self.difficulty))
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.message = Some((format!(" {message} "), Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    fn undo(&mut self) {
+        if self.on_pause || self.history.is_empty() {
+            return;
+        }
+        if self.undos_left() == Some(0) {
+            self.show_message("no undos left");
+            return;
+        }
+        self.undos_used += 1;
+        let (grid, score, moves) = self.history.pop().unwrap();
+        self.grid = grid;
+        self.score = score;
+        self.moves = moves;
+        // a swap is undone as well, it left the moves alone
+        self.recent_moves.retain(|&(number, _, _)| number <= moves);
+        self.panel_scroll = self.panel_scroll.min(self.recent_moves.len().saturating_sub(1));
+        if self.dead {
+            self.dead = false;
+            self.start_clock();
+        }
+        self.spawned = None;
+        self.pulse = None;
+        self.animating = false;
+        self.queued = None;
+        self.gained = None;
+        self.combo = None;
+        self.check_for_win();
+        self.autosave();
+        self.needs_redraw = true;
+    }
+
+    // snapshots the board into the working directory, a failed write is only reported
+    fn export_board(&mut self) {
+        let path = board_path("json");
+        match save_state(&path, &self.grid.values(), self.score) {
+            Ok(()) => self.show_message(&format!("saved {}", path.display())),
+            Err(err) => self.show_message(&format!("could not save the board: {err}"))
+        }
+    }
+
+    // a failed autosave is only reported, the game goes on and tries again with the next move
+    fn autosave(&mut self) {
+        let Some(path) = &self.autosave else {
+            return;
+        };
+        let saved = SavedGame {
+            board: self.grid.values(),
+            blocked: self.grid.blocked(),
+            score: self.score,
+            moves: self.moves,
+            mode: self.mode,
+            day: if self.mode == GameMode::Daily { self.records.daily_date } else { 0 },
+            difficulty: self.difficulty,
+            seed: self.game_seed,
+            played_secs: self.play_time().as_secs(),
+            undos_used: self.undos_used,
+            swaps_used: self.swaps_used,
+            continued: self.ignore_win,
+            set_up: self.practice || self.setup.is_some(),
+            zen: self.zen
+        };
+        if let Err(err) = save_game(path, &saved) {
+            self.show_message(&format!("could not autosave: {err}"));
+        }
+    }
+
+    // the code for --challenge, shown as well in case there is no clipboard
+    fn share_challenge(&mut self) {
+        if self.setup.is_some() {
+            self.show_message("a board set up by hand cannot be shared");
+            return;
+        }
+        let code = encode_challenge(self.game_seed, self.mode);
+        if copy_to_clipboard(&code).is_ok() {
+            self.show_message(&format!("copied challenge {code}"));
+        }
+        else {
+            self.show_message(&format!("challenge {code}"));
+        }
+    }
+
+    // the board as text, written to a file instead when there is no clipboard
+    fn copy_board(&mut self) {
+        let text = self.grid.to_string();
+        if copy_to_clipboard(&text).is_ok() {
+            self.show_message("copied the board");
+            return;
+        }
+        let path = board_path("txt");
+        match fs::write(&path, text) {
+            Ok(()) => self.show_message(&format!("no clipboard, saved {}", path.display())),
+            Err(err) => self.show_message(&format!("could not save the board: {err}"))
+        }
+    }
+
+    fn start_swap(&mut self) {
+        if self.on_pause || self.dead {
+            return;
+        }
+        if self.swaps_used >= self.swap_limit {
+            self.show_message("no swaps left");
+            return;
+        }
+        self.swapping = Some(None);
+        self.inspect = Some(0);
+        self.queued = None;
+        self.needs_redraw = true;
+    }
+
+    // Enter picks the field under the cursor, the second pick exchanges the two and uses up a swap.
+    // a field cannot be swapped with itself, blocked fields never move and two empty fields change nothing
+    fn handle_swap_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        let (Some(first), Some(at)) = (self.swapping, self.inspect) else {
+            return Ok(());
+        };
+        match key_event.code {
+            KeyCode::Char('q') => self.exit(),
+            KeyCode::Char('e') => self.export_board(),
+            KeyCode::Esc => {
+                self.swapping = None;
+                self.inspect = None;
+            }
+            KeyCode::Up | KeyCode::Right | KeyCode::Down | KeyCode::Left => self.move_inspect(key_event.code),
+            KeyCode::Enter if self.grid.fields[at].blocked => self.show_message("blocked fields stay where they are"),
+            KeyCode::Enter => match first {
+                None => self.swapping = Some(Some(at)),
+                Some(first) if first == at => self.swapping = Some(None),
+                Some(first) if self.grid.fields[first].val == 0 && self.grid.fields[at].val == 0 => {
+                    self.show_message("pick a tile, both fields are empty");
+                }
+                Some(first) => {
+                    if self.history.len() == UNDO_DEPTH {
+                        self.history.remove(0);
+                    }
+                    self.history.push((self.grid.clone(), self.score, self.moves));
+                    let val = self.grid.fields[first].val;
+                    self.grid.fields[first].val = self.grid.fields[at].val;
+                    self.grid.fields[at].val = val;
+                    self.swaps_used += 1;
+                    self.swapping = None;
+                    self.inspect = None;
+                    self.autosave();
+                    if !self.grid.has_moves() {
+                        self.is_dead()?;
+                    }
+                }
+            },
+            _ => {}
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    fn undos_left(&self) -> Option<u64> {
+        self.undo_limit.map(|limit| limit.saturating_sub(self.undos_used))
+    }
+
+    fn exit(&mut self) {
+        self.exit = true;
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.paused_by_focus = false;
+        self.on_pause = !self.on_pause;
+        self.inspect = self.on_pause.then_some(0);
+        if self.on_pause {
+            self.stop_clock();
+        }
+        else if !self.dead {
+            self.start_clock();
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    // alt-tabbing away pauses an ongoing game so no key meant for another window moves a tile
+    fn focus_lost(&mut self) -> Result<()> {
+        if self.menu || self.cursor.is_some() || self.swapping.is_some() || self.on_pause || self.dead {
+            return Ok(());
+        }
+        self.pause()?;
+        self.paused_by_focus = true;
+        Ok(())
+    }
+
+    // a pause the player picked is never ended by the focus coming back
+    fn focus_gained(&mut self) -> Result<()> {
+        if self.auto_resume && self.paused_by_focus && self.on_pause {
+            self.pause()?;
+        }
+        Ok(())
+    }
+
+    fn move_inspect(&mut self, code: KeyCode) {
+        let next = self.inspect
+            .zip(Direction::from_arrow(code))
+            .and_then(|(at, direction)| self.grid.fields[at].neighbours[direction.index()]);
+        if next.is_some() {
+            self.inspect = next;
+            self.needs_redraw = true;
+        }
+    }
+
+    fn is_dead(&mut self) -> Result<()> {
+        if !self.dead && !self.zen {
+            self.dead = true;
+            // the clock can run out halfway through picking a swap
+            if self.swapping.take().is_some() {
+                self.inspect = None;
+            }
+            self.displayed_score = self.score;
+            self.stop_clock();
+            self.feedback.on_gameover(self.score);
+        }
+        Ok(())
+    }
+
+    fn move_left(&mut self) -> Result<()> {
+        self.process_move(Direction::Left)
+    }
+
+    fn move_right(&mut self) -> Result<()> {
+        self.process_move(Direction::Right)
+    }
+
+    fn move_down(&mut self) -> Result<()> {
+        self.process_move(Direction::Down)
+    }
+
+    fn move_up(&mut self) -> Result<()> {
+        self.process_move(Direction::Up)
+    }
+
+    // single place where a move is applied and its consequences are resolved
+    fn process_move(&mut self, direction: Direction) -> Result<()> {
+        // a mouse drag or a queued key must not slide the tiles under an open legend or a half picked swap
+        if self.menu || self.cursor.is_some() || self.on_pause || self.dead || self.legend || self.swapping.is_some() {
+            return Ok(());
+        }
+        if self.animating {
+            self.queued = self.queued.or(Some(direction));
+            return Ok(());
+        }
+        // shown even when nothing moves, so the player sees the key arrived
+        if !self.reduced_motion {
+            self.last_move = Some((direction, Instant::now()));
+            self.needs_redraw = true;
+        }
+        let before = self.grid.clone();
+        let score = self.score;
+        let mut spawner = RandomSpawn { rng: &mut self.rng, difficulty: self.difficulty, rule: self.rule };
+        // nothing slid or merged, so this does not count as a move and spawns nothing.
+        // with room left the tiles already sit at that wall, on a full board no neighbours match
+        let Some((merged, spawned)) = self.grid.play(direction, &mut self.score, &mut spawner) else {
+            if self.grid.free_fields().is_empty() {
+                self.show_message(&format!("no move {direction}, no tiles merge that way"));
+            }
+            else {
+                self.show_message(&format!("no move {direction}, the tiles are already at the wall"));
+            }
+            return Ok(());
+        };
+        if self.history.len() == UNDO_DEPTH {
+            self.history.remove(0);
+        }
+        self.history.push((before, score, self.moves));
+        self.moves += 1;
+        let gained = self.score - score;
+        if self.recent_moves.len() == MOVE_PANEL_DEPTH {
+            self.recent_moves.remove(0);
+        }
+        self.recent_moves.push((self.moves, direction, gained));
+        self.panel_scroll = 0;
+        self.gained = (gained > 0).then(|| (gained, Instant::now()));
+        let merged_count = merged.len();
+        self.combo = (merged_count > 1).then(|| (merged_count, Instant::now()));
+        if !merged.is_empty() {
+            let tiles: Vec<u64> = merged.iter().map(|&i| self.grid.fields[i].val).collect();
+            self.feedback.on_merge(&tiles);
+        }
+        if !merged.is_empty() && !self.reduced_motion {
+            self.pulse = Some((merged, Instant::now()));
+            self.animating = true;
+        }
+        if let Some(i) = spawned {
+            self.feedback.on_spawn(self.grid.fields[i].val);
+            if !self.reduced_motion {
+                self.spawned = Some((i, Instant::now()));
+            }
+        }
+        if let Some(log) = self.log.as_mut() {
+            log.record(direction, gained, merged_count, &self.grid.values(), self.grid.hash())?;
+        }
+        self.autosave();
+        if merged_count > 0 {
+            // before highscore, it compares against the records as they were before this move
+            self.check_milestones();
+        }
+        self.highscore();
+        self.check_for_win();
+        if !self.grid.has_moves() || matches!(self.mode, GameMode::MoveLimit(limit) if self.moves >= limit) {
+            self.is_dead()?;
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    fn continue_game(&mut self) {
+        // a win on the last possible move stays a win
+        // before the target there is nothing to continue from, c must not turn the win off for later
+        if self.dead || !self.won {
+            return;
+        }
+        self.needs_redraw = true;
+        self.ignore_win = true;
+        self.check_for_win();
+    }
+
+    fn check_for_win(&mut self){
         if self.ignore_win {
             self.won = false;
             return;
         }
-        self.won = self.grid.get_state();
+        self.won = self.grid.get_state(self.goal());
     }
 
-    fn reset_max(&mut self) {
-        let _: () = self.grid.fields.iter_mut().map(|field|{
-            if field.as_ref().unwrap().val >= 2048 {
-                field.as_mut().unwrap().val = 0;
+    // best_tile is saved with the highscores, so a tile no game has reached before is celebrated as a first ever
+    fn check_milestones(&mut self) {
+        let counts = !(self.practice || self.setup.is_some() || self.zen || self.rule != Rule::Classic);
+        let max_tile = self.grid.max_tile();
+        for tile in MILESTONES.map(|tile| self.grid.rule.equivalent(tile)) {
+            if max_tile < tile || self.milestones.contains(&Milestone::Tile(tile)) {
+                continue;
+            }
+            self.milestones.push(Milestone::Tile(tile));
+            if !self.reduced_motion {
+                let message = if counts && tile > self.records.best_tile { format!("| first {tile} ever! ") } else { format!("| first {tile}! ") };
+                self.milestone = Some((message, Instant::now()));
             }
-        }).collect();
+        }
+        // the very first game has no best to beat
+        let best = self.best();
+        if counts && best > 0 && self.score > best && !self.milestones.contains(&Milestone::Best) {
+            self.milestones.push(Milestone::Best);
+            if !self.reduced_motion {
+                self.milestone = Some(("| new personal best! ".to_string(), Instant::now()));
+                self.record = Some(Instant::now());
+            }
+        }
+    }
+
+    // tiles already on a loaded or practice board are no milestone
+    fn reached_milestones(&self) -> Vec<Milestone> {
+        let max_tile = self.grid.max_tile();
+        MILESTONES.into_iter().map(|tile| self.grid.rule.equivalent(tile)).filter(|&tile| max_tile >= tile).map(Milestone::Tile).collect()
+    }
+
+    // the target in the sequence of the rule being played
+    fn goal(&self) -> u64 {
+        self.grid.rule.equivalent(self.target)
     }
 
 }
 
-#[derive(Debug, Default, Clone)]
-struct Grid {
-    fields: Vec<Option<Field>>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Milestone {
+    Tile(u64),
+    Best
 }
 
-impl Grid {
+// each mode keeps its own highscore, only one can be played at a time
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    // seconds on the clock
+    TimeAttack(u64),
+    // moves allowed
+    MoveLimit(u64),
+    // seeded with the date, the same board for everyone that day
+    Daily
+}
 
-    fn move_vals(&mut self, direction: usize, score: &mut u64) -> Result<()> {
-        if ![0,1,2,3].iter().any(|val| val == &direction) {
-            println!("exit");
-            return Ok(());
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameMode::Classic => write!(f, "classic"),
+            GameMode::TimeAttack(secs) => write!(f, "time attack {secs}s"),
+            GameMode::MoveLimit(moves) => write!(f, "{moves} moves"),
+            GameMode::Daily => write!(f, "daily")
         }
+    }
+}
+
+// how likely a new piece is worth more than 2, each keeps its own highscores
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard
+}
+
+impl Difficulty {
+    // the next harder or easier one, staying at the ends
+    fn step(self, harder: bool) -> Self {
+        match (self, harder) {
+            (Difficulty::Easy, true) | (Difficulty::Hard, false) => Difficulty::Normal,
+            (Difficulty::Normal, true) | (Difficulty::Hard, true) => Difficulty::Hard,
+            (Difficulty::Normal, false) | (Difficulty::Easy, false) => Difficulty::Easy
+        }
+    }
+
+    // easy only spawns 2s, normal a 4 one time in 10, hard a 4 one time in 4 and an 8 one time in 20
+    fn spawn_value(&self, rng: &mut StdRng) -> u64 {
+        let rand = rng.gen_range(0.0..1.0);
+        match self {
+            Difficulty::Easy => 2,
+            Difficulty::Normal if rand < 0.1 => 4,
+            Difficulty::Hard if rand < 0.05 => 8,
+            Difficulty::Hard if rand < 0.3 => 4,
+            _ => 2
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Normal => write!(f, "normal"),
+            Difficulty::Hard => write!(f, "hard")
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(GameError::BadArgs(format!("unknown difficulty `{s}`")))
+        }
+    }
+}
+
+// how tiles combine, the classic rule doubles equal tiles.
+// every sequence is numbered the same way so colors, milestones and the target carry over,
+// the first tile is 1, the classic 2048 is 11
+pub trait MergeRule {
+    // the tile two neighbours become, None if they do not merge
+    fn merge(&self, a: u64, b: u64) -> Option<u64>;
+    // the nth tile of the sequence
+    fn tile(&self, rank: u32) -> u64;
+    // where a tile is in the sequence, 0 for an empty field
+    fn rank(&self, val: u64) -> u32;
+    // a new piece, given what the classic rule would spawn
+    fn spawn(&self, classic: u64, rng: &mut StdRng) -> u64;
 
-        for _ in 0..4{
-            for i in 0..self.fields.len() {
-                let _ = recursive_merge(&Option::from(i), direction, &mut self.fields, score);
+    // the tile of this sequence at the same place as a classic one, 2048 is 144 with fib
+    fn equivalent(&self, classic: u64) -> u64 {
+        self.tile(classic.trailing_zeros())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    // 2 + 2 = 4, 4 + 4 = 8
+    #[default]
+    Classic,
+    // neighbours in 1, 2, 3, 5, 8, .. merge, and two 1s
+    Fib,
+    // 1 + 2 = 3, then equal tiles from 3 up double, 3 + 3 = 6
+    Threes
+}
+
+impl MergeRule for Rule {
+    fn merge(&self, a: u64, b: u64) -> Option<u64> {
+        let (low, high) = (a.min(b), a.max(b));
+        if low == 0 {
+            return None;
+        }
+        let merges = match self {
+            Rule::Classic => low == high,
+            Rule::Fib => (low == 1 && high == 1) || (1..).map(|rank| (self.tile(rank), self.tile(rank + 1))).take_while(|(tile, _)| *tile <= low).any(|pair| pair == (low, high)),
+            Rule::Threes => (low == 1 && high == 2) || (low == high && low >= 3)
+        };
+        merges.then_some(low + high)
+    }
+
+    fn tile(&self, rank: u32) -> u64 {
+        match self {
+            Rule::Classic => 1u64.checked_shl(rank).unwrap_or(u64::MAX),
+            Rule::Fib => {
+                let (mut tile, mut next) = (1u64, 2u64);
+                for _ in 1..rank {
+                    (tile, next) = (next, tile.saturating_add(next));
+                }
+                tile
             }
+            Rule::Threes if rank <= 2 => rank as u64,
+            Rule::Threes => 3u64.checked_shl(rank - 3).unwrap_or(u64::MAX)
         }
-        for field in self.fields.iter_mut() {
-            field.as_mut().unwrap().reset_blocker();
+    }
+
+    // tiles that are not in the sequence get the rank of the closest smaller one
+    fn rank(&self, val: u64) -> u32 {
+        match self {
+            _ if val == 0 => 0,
+            Rule::Classic => val.ilog2(),
+            Rule::Fib | Rule::Threes => (1..64).take_while(|rank| self.tile(*rank) <= val).last().unwrap_or(1)
         }
-        Ok(())
     }
 
-    fn new() -> Self {
-        let mut  grid = Grid {
-            fields: vec![Option::from(Field::new()); 16],
+    fn spawn(&self, classic: u64, rng: &mut StdRng) -> u64 {
+        match self {
+            Rule::Classic => classic,
+            Rule::Fib => self.equivalent(classic),
+            // 1s and 2s only merge with each other, so both come up equally often
+            Rule::Threes if classic == 2 => rng.gen_range(1..=2),
+            Rule::Threes => self.tile(classic.trailing_zeros() + 1)
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Classic => write!(f, "classic"),
+            Rule::Fib => write!(f, "fib"),
+            Rule::Threes => write!(f, "threes")
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Rule::Classic),
+            "fib" => Ok(Rule::Fib),
+            "threes" => Ok(Rule::Threes),
+            _ => Err(GameError::BadArgs(format!("unknown rule `{s}`")))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left
+}
+
+impl Direction {
+    fn arrow(&self, ascii: bool) -> char {
+        match (self, ascii) {
+            (Direction::Up, false) => '↑',
+            (Direction::Right, false) => '→',
+            (Direction::Down, false) => '↓',
+            (Direction::Left, false) => '←',
+            (Direction::Up, true) => '^',
+            (Direction::Right, true) => '>',
+            (Direction::Down, true) => 'v',
+            (Direction::Left, true) => '<'
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Direction::Up => "up",
+            Direction::Right => "right",
+            Direction::Down => "down",
+            Direction::Left => "left"
         };
+        write!(f, "{name}")
+    }
+}
 
+impl Direction {
+    // position of the neighbour in that direction in Field::neighbours
+    fn index(self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Right => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+        }
+    }
+
+    fn from_arrow(code: KeyCode) -> Option<Self> {
+        match code {
+            KeyCode::Up => Some(Direction::Up),
+            KeyCode::Right => Some(Direction::Right),
+            KeyCode::Down => Some(Direction::Down),
+            KeyCode::Left => Some(Direction::Left),
+            _ => None
+        }
+    }
+
+    // the inverse of Display, how a direction is written in the move log
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "up" => Some(Direction::Up),
+            "right" => Some(Direction::Right),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Grid {
+    fields: Vec<Field>,
+    rows: usize,
+    cols: usize,
+    rule: Rule
+}
+
+impl Grid {
+
+    pub fn get(&self, row: usize, col: usize) -> u64 {
+        self.field(row, col).val
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, val: u64) {
+        self.fields[row * self.cols + col].val = val;
+    }
+
+    // the indices of the blocked fields
+    pub fn blocked(&self) -> Vec<usize> {
+        (0..self.fields.len()).filter(|&i| self.fields[i].blocked).collect()
+    }
+
+    // blocks an empty field, a tile cannot sit on a blocked one
+    pub fn block(&mut self, i: usize) -> Result<(), GameError> {
+        match self.fields.get_mut(i) {
+            Some(field) if field.val == 0 => {
+                field.blocked = true;
+                Ok(())
+            }
+            Some(_) => Err(GameError::InvalidBoard(format!("field {i} holds a tile and cannot be blocked"))),
+            None => Err(GameError::InvalidBoard(format!("there is no field {i} to block")))
+        }
+    }
+
+    // row major snapshot of the tile values
+    pub fn values(&self) -> Vec<Vec<u64>> {
+        (0..self.rows)
+            .map(|row| (0..self.cols).map(|col| self.get(row, col)).collect())
+            .collect()
+    }
+
+    // FNV-1a over the tile values in row major order, it only depends on the board and stays the same
+    // across versions, so a replay that ends up somewhere else shows at the first move that differs
+    pub fn hash(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+        self.fields
+            .iter()
+            .flat_map(|field| field.val.to_le_bytes())
+            .fold(OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+    }
+
+    // the highscores are kept per size, which only square boards have
+    pub fn square(&self) -> Option<usize> {
+        (self.rows == self.cols).then_some(self.rows)
+    }
+
+    fn field(&self, row: usize, col: usize) -> &Field {
+        &self.fields[row * self.cols + col]
+    }
+
+    // a copy turned a quarter clockwise, rows and columns swap places
+    pub fn rotated(&self) -> Self {
+        self.transformed(self.cols, self.rows, |row, col| (self.rows - 1 - col, row))
+    }
+
+    // a copy flipped left to right
+    pub fn mirrored(&self) -> Self {
+        self.transformed(self.rows, self.cols, |row, col| (row, self.cols - 1 - col))
+    }
+
+    // builds a rows x cols board whose field at row, col is the one source gives on this board
+    fn transformed(&self, rows: usize, cols: usize, source: impl Fn(usize, usize) -> (usize, usize)) -> Self {
+        let mut grid = Grid {
+            fields: (0..rows * cols)
+                .map(|i| {
+                    let (row, col) = source(i / cols, i % cols);
+                    self.field(row, col).clone()
+                })
+                .collect(),
+            rows,
+            cols,
+            rule: self.rule
+        };
         Self::init_neighbours(&mut grid);
-        Self::init_grid(&mut grid);
+        grid
+    }
+
+    // one turn as App and Game both play it: slide, and only when that changed the board spawn a piece.
+    // None when nothing moved, otherwise the fields that merged and the one that got the new piece
+    fn play(&mut self, direction: Direction, score: &mut u64, spawner: &mut impl SpawnStrategy) -> Option<(Vec<usize>, Option<usize>)> {
+        let before = self.values();
+        let merged = self.slide(direction, score);
+        if self.values() == before {
+            return None;
+        }
+        Some((merged, self.spawn(spawner)))
+    }
+
+    // moves every tile as far as it goes towards the edge and returns the fields that hold a merged tile.
+    // per line the tiles are pushed together, neighbours that merge do so once starting at the edge,
+    // so 4 4 8 becomes 8 8 and not 16, and a merge is worth the tile it makes
+    pub fn slide(&mut self, direction: Direction, score: &mut u64) -> Vec<usize> {
+        let mut merged = vec![];
+        for line in self.lines(direction) {
+            // blocked fields split a line into parts that slide on their own
+            let parts: Vec<Vec<usize>> = line.split(|&i| self.fields[i].blocked).map(<[usize]>::to_vec).collect();
+            for part in parts {
+                let mut vals: Vec<u64> = vec![];
+                let mut can_merge = false;
+                for val in part.iter().map(|&i| self.fields[i].val).filter(|&val| val != 0) {
+                    match vals.last().filter(|_| can_merge).and_then(|&last| self.rule.merge(last, val)) {
+                        Some(tile) => {
+                            let last = vals.len() - 1;
+                            vals[last] = tile;
+                            *score += tile;
+                            merged.push(part[last]);
+                            can_merge = false;
+                        }
+                        _ => {
+                            vals.push(val);
+                            can_merge = true;
+                        }
+                    }
+                }
+                for (step, &i) in part.iter().enumerate() {
+                    self.fields[i].val = vals.get(step).copied().unwrap_or(0);
+                }
+            }
+        }
+        merged
+    }
+
+    // the field indices of every row or column, each starting at the edge the tiles move towards
+    pub fn lines(&self, direction: Direction) -> impl Iterator<Item = Vec<usize>> {
+        let (rows, cols) = (self.rows, self.cols);
+        // columns for up and down, rows for left and right
+        let (count, length) = match direction {
+            Direction::Up | Direction::Down => (cols, rows),
+            Direction::Left | Direction::Right => (rows, cols)
+        };
+        (0..count).map(move |line| {
+            (0..length)
+                .map(|step| match direction {
+                    Direction::Up => step * cols + line,
+                    Direction::Down => (rows - 1 - step) * cols + line,
+                    Direction::Left => line * cols + step,
+                    Direction::Right => line * cols + cols - 1 - step
+                })
+                .collect()
+        })
+    }
 
+    // the tiles that would merge when moving in direction, without moving anything.
+    // a copy makes the move so the preview follows the same rules as slide. every tile it leaves in
+    // a part of a line used up the next tile of that part, a merged one the next two, those merge
+    fn merge_preview(&self, direction: Direction) -> Vec<usize> {
+        let mut slid = self.clone();
+        let merged = slid.slide(direction, &mut 0);
+        let mut merging = vec![];
+        for line in self.lines(direction) {
+            for part in line.split(|&i| self.fields[i].blocked) {
+                let mut tiles = part.iter().copied().filter(|&i| self.fields[i].val != 0);
+                for i in part.iter().copied().filter(|&i| slid.fields[i].val != 0) {
+                    let first = tiles.next();
+                    if merged.contains(&i) {
+                        merging.extend(first.into_iter().chain(tiles.next()));
+                    }
+                }
+            }
+        }
+        merging
+    }
+
+    // places one new piece on a random free field and returns its index, None if the board is full
+    pub fn spawn(&mut self, spawner: &mut impl SpawnStrategy) -> Option<usize> {
+        let free = self.free_fields();
+        if free.is_empty() {
+            return None;
+        }
+        let i = spawner.pick(&free);
+        self.fields[i].val = spawner.value();
+        Some(i)
+    }
+
+    fn free_fields(&self) -> Vec<usize> {
+        (0..self.fields.len())
+            .filter(|i| self.fields[*i].is_free())
+            .collect()
+    }
+
+    fn new(rows: usize, cols: usize, blockers: usize, start_tiles: usize, rng: &mut StdRng) -> Self {
+        let mut grid = Self::empty(rows, cols);
+        Self::init_blockers(&mut grid, blockers, rng);
+        Self::init_grid(&mut grid, start_tiles, &mut RandomSpawn { rng, difficulty: Difficulty::Normal, rule: Rule::Classic });
+        grid
+    }
+
+    // a board without blockers whose start tiles go where spawner picks
+    pub fn with_spawner(rows: usize, cols: usize, start_tiles: usize, spawner: &mut impl SpawnStrategy) -> Self {
+        let mut grid = Self::empty(rows, cols);
+        Self::init_grid(&mut grid, start_tiles, spawner);
+        grid
+    }
+
+    fn empty(rows: usize, cols: usize) -> Self {
+        let mut grid = Grid {
+            fields: vec![Field::new(); rows * cols],
+            rows,
+            cols,
+            rule: Rule::Classic
+        };
+        Self::init_neighbours(&mut grid);
         grid
     }
 
-    fn init_grid(grid: &mut Self) {
-        let mut rng = thread_rng();
-        for field in grid.fields.iter_mut() {
-            let rand = rng.gen_range(0.0..1.0);
-            if field.as_ref().unwrap().val == 0 && rand < 0.1 {
-                field.as_mut().unwrap().val = 0;
+    // builds a board from rows of tile values, mostly useful to set up positions by hand
+    pub fn from_values(values: &[Vec<u64>]) -> Result<Self, GameError> {
+        let rows = values.len();
+        let cols = values.first().map_or(0, |row| row.len());
+        if cols == 0 {
+            return Err(GameError::InvalidBoard("the board is empty".to_string()));
+        }
+        if let Some(row) = values.iter().find(|row| row.len() != cols) {
+            return Err(GameError::InvalidBoard(format!("a row has {} values, expected {cols}", row.len())));
+        }
+        if let Some(val) = values.iter().flatten().find(|val| **val == 1 || !(**val == 0 || val.is_power_of_two())) {
+            return Err(GameError::InvalidBoard(format!("{val} is not a tile, tiles are 0 or a power of two of at least 2")));
+        }
+        let mut grid = Grid {
+            fields: vec![Field::new(); rows * cols],
+            rows,
+            cols,
+            rule: Rule::Classic
+        };
+        Self::init_neighbours(&mut grid);
+        for (row, vals) in values.iter().enumerate() {
+            for (col, val) in vals.iter().enumerate() {
+                grid.set(row, col, *val);
             }
         }
-        if grid.fields.iter().all(|field| field.as_ref().unwrap().val == 0) {
-            let random_index = rng.gen_range(0..=15);
-                grid.fields[random_index].as_mut().unwrap().val = 2;
+        Ok(grid)
+    }
+
+    // an empty board starts with a few 2s, as many as fit
+    fn init_grid(grid: &mut Self, start_tiles: usize, spawner: &mut impl SpawnStrategy) {
+        if grid.fields.iter().all(|field| field.val == 0) {
+            for _ in 0..start_tiles {
+                let free = grid.free_fields();
+                if free.is_empty() {
+                    break;
+                }
+                let i = spawner.pick(&free);
+                grid.fields[i].val = 2;
+            }
+        }
+    }
+
+    // at least half of the board is always left free to play on
+    fn init_blockers(grid: &mut Self, blockers: usize, rng: &mut StdRng) {
+        let mut placed = 0;
+        while placed < blockers.min(grid.fields.len() / 2) {
+            let random_index = rng.gen_range(0..grid.fields.len());
+            let field = &mut grid.fields[random_index];
+            if !field.blocked {
+                field.blocked = true;
+                placed += 1;
+            }
         }
     }
 
     fn init_neighbours(grid: &mut Self) {
+        let (rows, cols) = (grid.rows, grid.cols);
         for (i, field) in grid.fields.iter_mut().enumerate() {
-            let top: Option<usize>;
-            let right: Option<usize>;
-            let bot: Option<usize>;
-            let left: Option<usize>;
-            if i < 4 {
-                top = Option::from(None);
+            let top = if i < cols {
+                None
             }
             else {
-                top = Option::from(i - 4);
-            }
-            if [0, 4, 8, 12].iter().any(|val| val == &i) {
-                left = Option::from(None);
+                Some(i - cols)
+            };
+            let left = if i % cols == 0 {
+                None
             }
             else {
-                left = Option::from(i - 1);
-            }
-            if i > 11 {
-                bot = Option::from(None);
+                Some(i - 1)
+            };
+            let bot = if i >= cols * (rows - 1) {
+                None
             }
             else {
-                bot = Option::from(i + 4);
-            }
-            if [3, 7, 11, 15].iter().any(|val| val == &i) {
-                right = Option::from(None);
+                Some(i + cols)
+            };
+            let right = if i % cols == cols - 1 {
+                None
             }
             else {
-                right = Option::from(i + 1);
-            }
-            field.as_mut().unwrap().neighbours = vec![top, right, bot, left];
+                Some(i + 1)
+            };
+            field.neighbours = vec![top, right, bot, left];
         }
     }
 
-    fn get_state(&self) -> bool {
-        self.fields.iter().any(|field| field.as_ref().unwrap().val == 2048)
+    // false once no direction can slide or merge anything
+    pub fn has_moves(&self) -> bool {
+        self.fields.iter().filter(|field| !field.blocked).any(|field| {
+            field.val == 0 || field
+                .neighbours
+                .iter()
+                .flatten()
+                .map(|neighbour| &self.fields[*neighbour])
+                .any(|neighbour| !neighbour.blocked && self.rule.merge(neighbour.val, field.val).is_some())
+        })
+    }
+
+    fn max_tile(&self) -> u64 {
+        self.fields.iter().map(|field| field.val).max().unwrap_or(0)
     }
 
+    fn get_state(&self, target: u64) -> bool {
+        self.fields.iter().any(|field| field.val == target)
+    }
+
+}
+
+// decides where new pieces go and what they are worth, so a test can replace the rng
+// with a fixed sequence and know the exact board after every spawn
+pub trait SpawnStrategy {
+    // one of the free field indices, never called with none
+    fn pick(&mut self, free: &[usize]) -> usize;
+    fn value(&mut self) -> u64;
+}
+
+// what the game uses, every free field is equally likely
+struct RandomSpawn<'a> {
+    rng: &'a mut StdRng,
+    difficulty: Difficulty,
+    rule: Rule
+}
+
+impl SpawnStrategy for RandomSpawn<'_> {
+    fn pick(&mut self, free: &[usize]) -> usize {
+        free[self.rng.gen_range(0..free.len())]
+    }
+
+    fn value(&mut self) -> u64 {
+        let classic = self.difficulty.spawn_value(self.rng);
+        self.rule.spawn(classic, self.rng)
+    }
+}
+
+// one row per line, numbers right aligned, empty fields shown as `.` and blocked ones as `#`
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.max_tile().to_string().len();
+        for (row_index, row) in self.values().iter().enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(self.fields[row_index * self.cols..].iter())
+                .map(|(val, field)| match val {
+                    _ if field.blocked => format!("{:>width$}", "#"),
+                    0 => format!("{:>width$}", "."),
+                    _ => format!("{val:>width$}")
+                })
+                .collect();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 struct Field {
     val: u64,
     neighbours: Vec<Option<usize>>, // top right bottom left
-    has_merged: bool
+    // a wall for the whole game, it never holds a tile and nothing slides through it
+    blocked: bool
 }
 
 impl Field {
@@ -392,65 +2645,525 @@ impl Field {
         Field {
             val: 0,
             neighbours: vec![],
-            has_merged: false
+            blocked: false
         }
     }
 
-    fn check_for_merge(&self, next_val: u64) -> bool {
-        if (self.val == next_val || self.val == 0) && !self.has_merged {
-            return true;
-        }
-        false
+    fn is_free(&self) -> bool {
+        self.val == 0 && !self.blocked
     }
 
-    fn merge(&mut self, moving: u64, score: &mut u64) {
-        if self.val > 0 {
-            self.has_merged = true;
-            *score += self.val + moving;
+    // by the place in the sequence, so the classic 2048 and its equivalent in other rules look the same
+    fn get_color(&self, rule: Rule) -> Color {
+        match rule.rank(self.val) {
+            0 => Color::Black,
+            1 => Color::LightYellow,
+            2 => Color::White,
+            3 => Color::Blue,
+            4 => Color::Green,
+            5 => Color::Yellow,
+            6 => Color::Red,
+            7 => Color::Cyan,
+            8 => Color::LightMagenta,
+            9 => Color::Magenta,
+            10 => Color::LightBlue,
+            11 => Color::Rgb(255, 0, 255),
+            _ => Color::DarkGray,
         }
-        self.val += moving;
     }
+}
+
+// a game without a screen, for bots and replays. it builds the board and plays a turn
+// exactly like App, so the same seed plays the same game here and in the terminal
+#[derive(Debug)]
+pub struct Game {
+    grid: Grid,
+    score: u64,
+    rng: StdRng,
+    difficulty: Difficulty
+}
 
-    fn reset_blocker(&mut self) {
-        self.has_merged = false;
+impl Game {
+    pub fn new(seed: Option<u64>, rows: usize, cols: usize, difficulty: Difficulty, blockers: usize, start_tiles: usize) -> Self {
+        let mut rng = new_rng(seed);
+        Game {
+            grid: Grid::new(rows, cols, blockers, start_tiles, &mut rng),
+            score: 0,
+            rng,
+            difficulty
+        }
     }
 
-    fn get_color(&self) -> Color {
-        match self.val {
-            0 => return Color::Black,
-            2 => return Color::LightYellow,
-            4 => return Color::White,
-            8 => return Color::Blue,
-            16 => return Color::Green,
-            32 => return Color::Yellow,
-            64 => return Color::Red,
-            128 => return Color::Cyan,
-            256 => return Color::LightMagenta,
-            512 => return Color::Magenta,
-            1024 => return Color::LightBlue,
-            2024 => return  Color::Rgb(255, 0, 255),
-            _ => return  Color::DarkGray,
+    // a game whose start tiles come from spawner, play it with apply_move_with and the same spawner
+    // to know the exact board after every move
+    pub fn with_spawner(rows: usize, cols: usize, start_tiles: usize, spawner: &mut impl SpawnStrategy) -> Self {
+        Game {
+            grid: Grid::with_spawner(rows, cols, start_tiles, spawner),
+            score: 0,
+            rng: new_rng(None),
+            difficulty: Difficulty::Normal
         }
     }
+
+    // returns whether anything moved, a move that changes nothing spawns nothing
+    pub fn apply_move(&mut self, direction: Direction) -> bool {
+        let mut spawner = RandomSpawn { rng: &mut self.rng, difficulty: self.difficulty, rule: self.grid.rule };
+        self.grid.play(direction, &mut self.score, &mut spawner).is_some()
+    }
+
+    // apply_move with the new piece placed by spawner instead of the seed
+    pub fn apply_move_with(&mut self, direction: Direction, spawner: &mut impl SpawnStrategy) -> bool {
+        self.grid.play(direction, &mut self.score, spawner).is_some()
+    }
+
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn is_over(&self) -> bool {
+        !self.grid.has_moves()
+    }
+}
+
+// plays moves on game and returns the final score and board. a game from Game::new with the seed and
+// board options of a terminal game draws from the rng in the same order that game did, so the same
+// game and moves always give the same result, which is what makes it usable to check a claimed score.
+// only classic rule games started from the menu can be replayed, practice and loaded boards cannot.
+// moves that change nothing are skipped just like in the game
+pub fn verify_replay(mut game: Game, moves: &[Direction]) -> (u64, Grid) {
+    for direction in moves {
+        game.apply_move(*direction);
+    }
+    (game.score, game.grid)
+}
+
+// replays a move log on game, see verify_replay for the games that works for. the log only has the moves
+// that changed the board, so one that changes nothing here is a desync just like a board with another hash.
+// returns the index of the first entry that differs, None when all of them match
+pub fn find_desync(mut game: Game, log: &[(Direction, u64)]) -> Option<usize> {
+    log.iter().position(|&(direction, hash)| !game.apply_move(direction) || game.grid.hash() != hash)
 }
 
-fn recursive_merge(mv_field: &Option<usize>, direction: usize, fields: &mut Vec<Option<Field>>, score: &mut u64) -> Result<bool> {
-    match mv_field {
-        None => return Ok(false),
-        Some(field) => {
-            let next_index = &fields[*field].as_ref().unwrap().neighbours[direction].clone();
-            let is_movable = recursive_merge(next_index, direction, fields, score)?;
-            if !is_movable {
-                return Ok(true);
+// a new file name in the working directory for every snapshot
+fn board_path(extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    PathBuf::from(format!("board-{millis}.{extension}"))
+}
+
+fn check_dimensions(rows: usize, cols: usize) -> Result<(), GameError> {
+    if !(MIN_SIZE..=MAX_SIZE).contains(&rows) || !(MIN_SIZE..=MAX_SIZE).contains(&cols) {
+        return Err(GameError::InvalidBoard(format!("a {cols}x{rows} board is not between {MIN_SIZE}x{MIN_SIZE} and {MAX_SIZE}x{MAX_SIZE}")));
+    }
+    Ok(())
+}
+
+// blockers take at most half the board, the start tiles have to fit in the rest
+pub fn check_start_tiles(start_tiles: usize, rows: usize, cols: usize, blockers: usize) -> Result<(), GameError> {
+    let free = rows * cols - blockers.min(rows * cols / 2);
+    if !(1..=free).contains(&start_tiles) {
+        return Err(GameError::BadArgs(format!("`--start-tiles` must be between 1 and {free} on this board, got {start_tiles}")));
+    }
+    Ok(())
+}
+
+fn new_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy()
+    }
+}
+
+// the brighter counterpart of a tile color, used to flash merged tiles
+fn lighter(color: Color) -> Color {
+    match color {
+        Color::Black => Color::DarkGray,
+        Color::Blue => Color::LightBlue,
+        Color::Green => Color::LightGreen,
+        Color::Yellow => Color::LightYellow,
+        Color::Red => Color::LightRed,
+        Color::Cyan => Color::LightCyan,
+        Color::Magenta => Color::LightMagenta,
+        Color::DarkGray => Color::Gray,
+        Color::Rgb(r, g, b) => Color::Rgb(r.saturating_add(64), g.saturating_add(64), b.saturating_add(64)),
+        _ => Color::White
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // always the first free field and always a 2, for boards that are known move by move
+    struct FixedSpawn;
+
+    impl SpawnStrategy for FixedSpawn {
+        fn pick(&mut self, free: &[usize]) -> usize {
+            free[0]
+        }
+
+        fn value(&mut self) -> u64 {
+            2
+        }
+    }
+
+    #[test]
+    fn fixed_spawn_gives_an_exact_board() {
+        let mut game = Game::with_spawner(4, 4, 2, &mut FixedSpawn);
+        assert_eq!(game.grid().values(), vec![vec![2, 2, 0, 0], vec![0; 4], vec![0; 4], vec![0; 4]]);
+        assert!(game.apply_move_with(Direction::Right, &mut FixedSpawn));
+        assert_eq!(game.grid().values(), vec![vec![2, 0, 0, 4], vec![0; 4], vec![0; 4], vec![0; 4]]);
+        assert!(game.apply_move_with(Direction::Down, &mut FixedSpawn));
+        assert_eq!(game.grid().values(), vec![vec![2, 0, 0, 0], vec![0; 4], vec![0; 4], vec![2, 0, 0, 4]]);
+        assert!(game.apply_move_with(Direction::Left, &mut FixedSpawn));
+        assert_eq!(game.grid().values(), vec![vec![2, 2, 0, 0], vec![0; 4], vec![0; 4], vec![2, 4, 0, 0]]);
+        assert_eq!(game.score(), 4);
+        assert!(game.apply_move_with(Direction::Left, &mut FixedSpawn));
+        assert_eq!(game.grid().values(), vec![vec![4, 2, 0, 0], vec![0; 4], vec![0; 4], vec![2, 4, 0, 0]]);
+        assert_eq!(game.score(), 8);
+    }
+
+    const MOVES: [Direction; 12] = [
+        Direction::Left, Direction::Up, Direction::Right, Direction::Down,
+        Direction::Left, Direction::Left, Direction::Up, Direction::Up,
+        Direction::Right, Direction::Down, Direction::Down, Direction::Left
+    ];
+
+    fn seeded(seed: u64) -> Game {
+        Game::new(Some(seed), 4, 4, Difficulty::Normal, 0, START_TILES)
+    }
+
+    // the moves that changed the board and the hash after each, the way MoveLog has them
+    fn log_of(mut game: Game, moves: &[Direction]) -> Vec<(Direction, u64)> {
+        let mut log = vec![];
+        for &direction in moves {
+            if game.apply_move(direction) {
+                log.push((direction, game.grid().hash()));
+            }
+        }
+        log
+    }
+
+    #[test]
+    fn replays_are_deterministic() {
+        let (score, grid) = verify_replay(seeded(42), &MOVES);
+        for _ in 0..3 {
+            let (again, again_grid) = verify_replay(seeded(42), &MOVES);
+            assert_eq!(again, score);
+            assert_eq!(again_grid.values(), grid.values());
+        }
+    }
+
+    #[test]
+    fn a_log_of_the_same_game_matches() {
+        let log = log_of(seeded(42), &MOVES);
+        assert!(!log.is_empty());
+        assert_eq!(find_desync(seeded(42), &log), None);
+    }
+
+    #[test]
+    fn a_changed_log_is_found() {
+        let mut log = log_of(seeded(42), &MOVES);
+        log[3].1 ^= 1;
+        assert_eq!(find_desync(seeded(42), &log), Some(3));
+        // a game on another seed already spawns its tiles elsewhere
+        assert_eq!(find_desync(seeded(43), &log_of(seeded(42), &MOVES)), Some(0));
+    }
+
+    #[test]
+    fn build_rejects_values_that_would_panic_later() {
+        assert!(AppConfig::default().build().is_ok());
+        assert!(AppConfig { fps: 0, ..Default::default() }.build().is_err());
+        assert!(AppConfig { anim_speed: 0.0, ..Default::default() }.build().is_err());
+        assert!(AppConfig { anim_speed: 10.5, ..Default::default() }.build().is_err());
+        assert!(AppConfig { aspect: 0.0, ..Default::default() }.build().is_ok());
+        assert!(AppConfig { aspect: 0.2, ..Default::default() }.build().is_err());
+        assert!(AppConfig { aspect: f64::NAN, ..Default::default() }.build().is_err());
+        assert!(AppConfig { aspect: 4.5, ..Default::default() }.build().is_err());
+        assert!(AppConfig { start_tiles: 0, ..Default::default() }.build().is_err());
+        assert!(AppConfig { start_tiles: 17, ..Default::default() }.build().is_err());
+        assert!(AppConfig { start_tiles: 12, blockers: 4, ..Default::default() }.build().is_ok());
+        assert!(AppConfig { start_tiles: 13, blockers: 4, ..Default::default() }.build().is_err());
+        assert!(AppConfig { target: 4, ..Default::default() }.build().is_err());
+        assert!(AppConfig { target: 100, ..Default::default() }.build().is_err());
+        assert!(AppConfig { target: 8, ..Default::default() }.build().is_ok());
+    }
+
+    #[test]
+    fn every_field_knows_its_neighbours() {
+        for (rows, cols) in [(4, 4), (3, 5), (8, 3)] {
+            let grid = Grid::from_values(&vec![vec![0; cols]; rows]).unwrap();
+            for (i, field) in grid.fields.iter().enumerate() {
+                let (row, col) = (i / cols, i % cols);
+                let top = (row > 0).then(|| i - cols);
+                let right = (col < cols - 1).then(|| i + 1);
+                let bottom = (row < rows - 1).then(|| i + cols);
+                let left = (col > 0).then(|| i - 1);
+                assert_eq!(field.neighbours, vec![top, right, bottom, left], "field {i} of {cols}x{rows}");
             }
-            let current_val = fields[*field].as_ref().unwrap().val.clone();
-            let next_field = fields[next_index.unwrap()].as_mut().unwrap();
-            let can_move = next_field.check_for_merge(current_val);
-            if can_move {
-                next_field.merge(current_val, score);
-                fields[*field].as_mut().unwrap().val = 0;
+        }
+    }
+
+    #[test]
+    fn a_merge_scores_the_tile_it_makes_once() {
+        let mut grid = Grid::from_values(&[vec![2, 2, 4, 4]]).unwrap();
+        let mut score = 0;
+        grid.slide(Direction::Left, &mut score);
+        assert_eq!(grid.values(), vec![vec![4, 8, 0, 0]]);
+        assert_eq!(score, 12);
+        // the 4 and 8 do not merge, a second move scores nothing
+        grid.slide(Direction::Left, &mut score);
+        assert_eq!(score, 12);
+    }
+
+    // a one line board holding line read from the wall direction slides towards
+    fn line_towards(direction: Direction, line: &[u64]) -> Grid {
+        let mut line = line.to_vec();
+        if matches!(direction, Direction::Right | Direction::Down) {
+            line.reverse();
+        }
+        match direction {
+            Direction::Left | Direction::Right => Grid::from_values(&[line]).unwrap(),
+            Direction::Up | Direction::Down => Grid::from_values(&line.iter().map(|&val| vec![val]).collect::<Vec<_>>()).unwrap()
+        }
+    }
+
+    #[test]
+    fn a_merged_tile_does_not_merge_again_in_the_same_move() {
+        let cases: [(&[u64], &[u64]); 3] = [
+            (&[4, 4, 8], &[8, 8, 0]),
+            (&[2, 2, 4, 4], &[4, 8, 0, 0]),
+            (&[8, 8, 8, 8], &[16, 16, 0, 0])
+        ];
+        for direction in [Direction::Up, Direction::Right, Direction::Down, Direction::Left] {
+            for (line, expected) in cases {
+                let mut grid = line_towards(direction, line);
+                grid.slide(direction, &mut 0);
+                assert_eq!(grid.values(), line_towards(direction, expected).values(), "{line:?} {direction}");
             }
         }
     }
-    Ok(true)
+
+    #[test]
+    fn lines_start_at_the_wall() {
+        let grid = Grid::from_values(&vec![vec![0; 4]; 4]).unwrap();
+        let lines = |direction| grid.lines(direction).collect::<Vec<_>>();
+        assert_eq!(lines(Direction::Up), vec![vec![0, 4, 8, 12], vec![1, 5, 9, 13], vec![2, 6, 10, 14], vec![3, 7, 11, 15]]);
+        assert_eq!(lines(Direction::Down), vec![vec![12, 8, 4, 0], vec![13, 9, 5, 1], vec![14, 10, 6, 2], vec![15, 11, 7, 3]]);
+        assert_eq!(lines(Direction::Left), vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9, 10, 11], vec![12, 13, 14, 15]]);
+        assert_eq!(lines(Direction::Right), vec![vec![3, 2, 1, 0], vec![7, 6, 5, 4], vec![11, 10, 9, 8], vec![15, 14, 13, 12]]);
+    }
+
+    #[test]
+    fn lines_of_an_oblong_board() {
+        // 2 rows of 3
+        let grid = Grid::from_values(&vec![vec![0; 3]; 2]).unwrap();
+        let lines = |direction| grid.lines(direction).collect::<Vec<_>>();
+        assert_eq!(lines(Direction::Up), vec![vec![0, 3], vec![1, 4], vec![2, 5]]);
+        assert_eq!(lines(Direction::Down), vec![vec![3, 0], vec![4, 1], vec![5, 2]]);
+        assert_eq!(lines(Direction::Left), vec![vec![0, 1, 2], vec![3, 4, 5]]);
+        assert_eq!(lines(Direction::Right), vec![vec![2, 1, 0], vec![5, 4, 3]]);
+    }
+
+    #[test]
+    fn nothing_moves_against_the_wall() {
+        let values = vec![vec![2, 4, 8, 16], vec![0; 4], vec![0; 4], vec![0; 4]];
+        let mut grid = Grid::from_values(&values).unwrap();
+        grid.slide(Direction::Up, &mut 0);
+        assert_eq!(grid.values(), values);
+
+        let mut game = Game { grid: Grid::from_values(&values).unwrap(), score: 0, rng: new_rng(Some(1)), difficulty: Difficulty::Normal };
+        assert!(!game.apply_move(Direction::Up));
+        assert_eq!(game.grid().values(), values);
+
+        let mut app = App::new(Some(1), 4).unwrap();
+        app.load(Grid::from_values(&values).unwrap()).unwrap();
+        app.move_up().unwrap();
+        assert_eq!(app.moves, 0);
+        assert_eq!(app.grid.values(), values);
+    }
+
+    #[test]
+    fn every_free_field_is_picked_as_often() {
+        const PICKS: usize = 16_000;
+        let free: Vec<usize> = (0..16).collect();
+        let mut rng = new_rng(Some(7));
+        let mut spawner = RandomSpawn { rng: &mut rng, difficulty: Difficulty::Normal, rule: Rule::Classic };
+        let mut counts = [0usize; 16];
+        for _ in 0..PICKS {
+            counts[spawner.pick(&free)] += 1;
+        }
+        // 1000 expected per field, a standard deviation is about 31
+        let expected = PICKS / free.len();
+        for (i, count) in counts.iter().enumerate() {
+            assert!(count.abs_diff(expected) < 150, "field {i} was picked {count} times out of {PICKS}");
+        }
+    }
+
+    #[test]
+    fn four_rotations_and_two_mirrors_give_the_board_back() {
+        let values = vec![vec![2, 4, 8], vec![16, 0, 32], vec![64, 128, 0], vec![0, 256, 2]];
+        let grid = Grid::from_values(&values).unwrap();
+        let quarter = grid.rotated();
+        // 4 rows of 3 turn into 3 rows of 4, the bottom left tile ends up top left
+        assert_eq!(quarter.values(), vec![vec![0, 64, 16, 2], vec![256, 128, 0, 4], vec![2, 0, 32, 8]]);
+        assert_eq!(quarter.rotated().rotated().rotated().values(), values);
+        assert_eq!(grid.mirrored().values(), vec![vec![8, 4, 2], vec![32, 0, 16], vec![0, 128, 64], vec![2, 256, 0]]);
+        assert_eq!(grid.mirrored().mirrored().values(), values);
+    }
+
+    // a fixed game on a known seed, anything that changes spawning, merging or scoring changes the end
+    #[test]
+    fn a_scripted_game_ends_the_same_way() {
+        let mut game = seeded(2048);
+        let script = [Direction::Down, Direction::Left, Direction::Down, Direction::Right, Direction::Up];
+        let mut moves = 0;
+        while !game.is_over() && moves < 2000 {
+            game.apply_move(script[moves % script.len()]);
+            moves += 1;
+        }
+        assert!(game.is_over());
+        assert_eq!(moves, 82);
+        assert_eq!(game.score(), 596);
+        assert_eq!(game.grid().values(), vec![vec![2, 4, 2, 4], vec![4, 32, 16, 2], vec![8, 16, 64, 4], vec![2, 8, 2, 16]]);
+    }
+
+    // the move that makes the target also leaves no move, the game ends as won
+    #[test]
+    fn a_move_that_wins_and_loses_counts_as_won() {
+        let board = Grid::from_values(&[vec![64, 64, 16], vec![2, 8, 32], vec![16, 2, 4]]).unwrap();
+        let mut app = AppConfig { seed: Some(1), size: 3, target: 128, ..Default::default() }.build().unwrap();
+        app.load(board).unwrap();
+        assert!(!app.won && !app.dead);
+        app.process_move(Direction::Left).unwrap();
+        assert_eq!(app.grid.values()[0][..2], [128, 16]);
+        assert!(app.won && app.dead);
+    }
+
+    #[test]
+    fn boards_outside_the_sizes_are_errors() {
+        assert!(App::new(None, 0).is_err());
+        assert!(App::new(None, 9).is_err());
+        assert!(App::new(None, MIN_SIZE).is_ok());
+        assert!(App::new(None, MAX_SIZE).is_ok());
+        assert!(AppConfig { shape: Some((5, 2)), ..Default::default() }.build().is_err());
+        assert!(AppConfig { shape: Some((5, 3)), ..Default::default() }.build().is_ok());
+    }
+
+    #[test]
+    fn nothing_slides_while_picking_a_swap() {
+        let values = vec![vec![2, 0, 0, 4], vec![0; 4], vec![0; 4], vec![0, 0, 8, 0]];
+        let mut app = AppConfig { swap_limit: 1, ..Default::default() }.build().unwrap();
+        app.load(Grid::from_values(&values).unwrap()).unwrap();
+        app.queued = Some(Direction::Left);
+        app.start_swap();
+        assert_eq!(app.queued, None);
+        app.process_move(Direction::Left).unwrap();
+        assert_eq!(app.grid.values(), values);
+        assert_eq!(app.moves, 0);
+    }
+
+    #[test]
+    fn continuing_before_the_target_keeps_the_win() {
+        let mut app = AppConfig { target: 8, ..Default::default() }.build().unwrap();
+        app.load(Grid::from_values(&[vec![4, 4, 0, 0], vec![0; 4], vec![0; 4], vec![0; 4]]).unwrap()).unwrap();
+        app.continue_game();
+        assert!(!app.ignore_win);
+        app.process_move(Direction::Left).unwrap();
+        assert!(app.won);
+        app.continue_game();
+        assert!(app.ignore_win && !app.won);
+    }
+
+    #[test]
+    fn doubling_a_tile_in_setup_stops_at_the_largest_one() {
+        let mut app = AppConfig { practice: true, ..Default::default() }.build().unwrap();
+        app.new_game().unwrap();
+        for _ in 0..70 {
+            app.handle_setup_key(KeyEvent::from(KeyCode::Char('+'))).unwrap();
+        }
+        assert_eq!(app.grid.fields[0].val, 1 << 63);
+    }
+
+    #[test]
+    fn the_preview_shows_the_tiles_the_move_merges() {
+        let mut grid = Grid::from_values(&[vec![2, 2, 4, 4], vec![4, 4, 8, 0], vec![2, 0, 2, 2], vec![8, 8, 8, 8]]).unwrap();
+        assert_eq!(grid.merge_preview(Direction::Left), vec![0, 1, 2, 3, 4, 5, 8, 10, 12, 13, 14, 15]);
+        assert_eq!(grid.merge_preview(Direction::Right), vec![3, 2, 1, 0, 5, 4, 11, 10, 15, 14, 13, 12]);
+        // a blocked field keeps the 2 in front of it from the two behind it
+        grid.fields[9].blocked = true;
+        assert_eq!(grid.merge_preview(Direction::Left), vec![0, 1, 2, 3, 4, 5, 10, 11, 12, 13, 14, 15]);
+        assert!(Grid::from_values(&[vec![2, 4], vec![4, 2]]).unwrap().merge_preview(Direction::Up).is_empty());
+    }
+
+    // the record of a move made right before quitting is in the file main saves on the way out
+    #[test]
+    fn a_record_set_before_quitting_is_saved() {
+        let dir = std::env::temp_dir().join(format!("game_2048-quit-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Highscore.bin");
+        let mut records = Records::default();
+        records.highscores.insert((GameMode::Classic, 4, Difficulty::Normal), 4);
+        let mut app = AppConfig { seed: Some(3), records, ..Default::default() }.build().unwrap();
+        app.new_game().unwrap();
+        app.menu = false;
+        for direction in MOVES.iter().cycle().take(100) {
+            app.process_move(*direction).unwrap();
+            // the merge pulse holds back the next move
+            app.animating = false;
+            if app.score > 4 {
+                break;
+            }
+        }
+        assert!(app.score > 4);
+        app.exit();
+        save_records(&path, &app.records).unwrap();
+        assert_eq!(read_records(&path).unwrap().highscore(GameMode::Classic, 4, Difficulty::Normal), app.score);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_resumed_game_goes_on_as_the_same_game() {
+        let dir = std::env::temp_dir().join(format!("game_2048-resume-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Autosave.json");
+        let saved = SavedGame {
+            board: vec![vec![2, 2, 0, 0], vec![0; 4], vec![0, 0, 0, 0], vec![0, 0, 0, 4]],
+            blocked: vec![10],
+            score: 500,
+            moves: 40,
+            mode: GameMode::MoveLimit(100),
+            day: 0,
+            difficulty: Difficulty::Easy,
+            seed: 9,
+            played_secs: 30,
+            undos_used: 0,
+            swaps_used: 0,
+            continued: false,
+            set_up: false,
+            zen: false
+        };
+        let mut app = AppConfig { autosave: Some(path.clone()), resume: Some(saved), ..Default::default() }.build().unwrap();
+        assert_eq!((app.score, app.moves, app.mode, app.difficulty), (500, 40, GameMode::MoveLimit(100), Difficulty::Easy));
+        assert!(app.grid.fields[10].blocked);
+        assert!(app.play_time() >= Duration::from_secs(30));
+
+        app.process_move(Direction::Left).unwrap();
+        assert_eq!((app.score, app.moves), (504, 41));
+        // it is a normal game, not one set up by hand, so the record counts
+        assert_eq!(app.records.highscore(GameMode::MoveLimit(100), 4, Difficulty::Easy), 504);
+        let autosaved = load_game(&path).unwrap();
+        assert_eq!((autosaved.score, autosaved.moves, autosaved.blocked), (504, 41, vec![10]));
+
+        // and the file follows an undo as well
+        app.undo();
+        assert_eq!(load_game(&path).unwrap().score, 500);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
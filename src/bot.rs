@@ -0,0 +1,60 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+
+use crate::app::{Direction, Game};
+use crate::errors::GameError;
+use crate::read_write::board_json;
+
+// lets another program play through a stream of lines, see --bot in the usage.
+// every line is a command: up, right, down or left, or just their first letter, and quit.
+// the state is written as one line of JSON at the start and after every move:
+// {"score":0,"moved":false,"over":false,"board":[[2,0,0,0],..]}
+// moved is false when the move changed nothing, over is true once no move is left.
+// a line that is not a command is answered with {"error":"unknown command"}
+pub fn play(input: impl BufRead, mut output: impl Write, game: &mut Game) -> Result<(), GameError> {
+    writeln!(output, "{}", state_json(game, false))?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let direction = match line.trim() {
+            "" => continue,
+            "quit" | "q" => break,
+            "up" | "u" => Direction::Up,
+            "right" | "r" => Direction::Right,
+            "down" | "d" => Direction::Down,
+            "left" | "l" => Direction::Left,
+            _ => {
+                writeln!(output, "{{\"error\":\"unknown command\"}}")?;
+                output.flush()?;
+                continue;
+            }
+        };
+        let moved = game.apply_move(direction);
+        writeln!(output, "{}", state_json(game, moved))?;
+        // the other side usually waits for the answer before it sends the next move
+        output.flush()?;
+    }
+    Ok(())
+}
+
+// --serve, the same protocol over TCP on localhost. clients are served one after the other,
+// each plays a fresh game and a disconnect ends it, new_game builds those games
+pub fn serve(port: u16, mut new_game: impl FnMut() -> Game) -> Result<(), GameError> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let mut game = new_game();
+        // a client going away mid game is normal, the next one is served anyway
+        let _ = play(BufReader::new(stream.try_clone()?), stream, &mut game);
+    }
+    Ok(())
+}
+
+fn state_json(game: &Game, moved: bool) -> String {
+    format!(
+        "{{\"score\":{},\"moved\":{moved},\"over\":{},\"board\":{}}}",
+        game.score(),
+        game.is_over(),
+        board_json(&game.grid().values())
+    )
+}
@@ -0,0 +1,80 @@
+use crate::app::GameMode;
+use crate::errors::GameError;
+
+// crockford's base32, no I, L, O or U so a code read out loud or typed by hand is hard to get wrong
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+// a u64 takes 13 base32 digits
+const SEED_DIGITS: usize = 13;
+
+// a code like C0000000000016 for someone else to play the same game with --challenge:
+// a letter for the mode, the seed in 13 digits and then the seconds or moves of a timed or limited game.
+// a daily game is shared as a classic one on its seed, it is only the daily on the day it was played
+pub fn encode_challenge(seed: u64, mode: GameMode) -> String {
+    let (letter, parameter) = match mode {
+        GameMode::Classic | GameMode::Daily => ('C', None),
+        GameMode::TimeAttack(secs) => ('T', Some(secs)),
+        GameMode::MoveLimit(moves) => ('M', Some(moves))
+    };
+    let mut code = letter.to_string();
+    code += &format!("{:0>SEED_DIGITS$}", base32(seed));
+    if let Some(parameter) = parameter {
+        code += &base32(parameter);
+    }
+    code
+}
+
+// the inverse of encode_challenge, lowercase and the usual misreadings of 0 and 1 are accepted
+pub fn decode_challenge(code: &str) -> Result<(u64, GameMode), GameError> {
+    let invalid = |reason: &str| GameError::InvalidChallenge(code.to_string(), reason.to_string());
+    let mut chars = code.trim().chars();
+    let letter = chars.next().ok_or_else(|| invalid("it is empty"))?.to_ascii_uppercase();
+    let digits: String = chars.collect();
+    if !digits.is_ascii() {
+        return Err(invalid("it is not base32"));
+    }
+    if digits.len() < SEED_DIGITS {
+        return Err(invalid("it is too short"));
+    }
+    let (seed, parameter) = digits.split_at(SEED_DIGITS);
+    let seed = from_base32(seed).ok_or_else(|| invalid("the seed is not base32 or too large"))?;
+    let number = || match from_base32(parameter) {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(invalid("the mode needs a number after the seed"))
+    };
+    let mode = match letter {
+        'C' if parameter.is_empty() => GameMode::Classic,
+        'C' => return Err(invalid("a classic game has nothing after the seed")),
+        'T' => GameMode::TimeAttack(number()?),
+        'M' => GameMode::MoveLimit(number()?),
+        _ => return Err(invalid("it does not start with C, T or M"))
+    };
+    Ok((seed, mode))
+}
+
+fn base32(mut n: u64) -> String {
+    let mut digits = vec![];
+    loop {
+        digits.push(ALPHABET[(n % 32) as usize] as char);
+        n /= 32;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+// None for an empty string, a character outside the alphabet or a number that does not fit a u64
+fn from_base32(digits: &str) -> Option<u64> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.chars().try_fold(0u64, |n, c| {
+        let c = match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c
+        };
+        let digit = ALPHABET.iter().position(|&a| a as char == c)? as u64;
+        n.checked_mul(32)?.checked_add(digit)
+    })
+}
@@ -1,4 +1,5 @@
-use std::panic;
+use std::{fmt, io, panic};
+use std::path::PathBuf;
 
 use color_eyre::{config::HookBuilder, eyre};
 
@@ -9,17 +10,64 @@ pub fn install_hooks() -> color_eyre::Result<()> {
 
     let panic_hook = panic_hook.into_panic_hook();
     panic::set_hook(Box::new(move |panic_info| {
-        tui::restore().unwrap();
+        // a failing restore must not panic again, or the report below is never printed
+        let _ = tui::restore();
         panic_hook(panic_info);
     }));
 
     let eyre_hook = eyre_hook.into_eyre_hook();
     eyre::set_hook(Box::new(
         move | error: &(dyn std::error::Error + 'static)| {
-            tui::restore().unwrap();
+            let _ = tui::restore();
             eyre_hook(error)
         },
     ))?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Debug)]
+pub enum GameError {
+    Io(io::Error),
+    CorruptSave(PathBuf),
+    BadArgs(String),
+    InvalidBoard(String),
+    // the file and the line that could not be read
+    BadPrefs(PathBuf, String),
+    // a file the game keeps is a directory instead
+    IsDirectory(PathBuf),
+    // the code given with --challenge and what is wrong with it
+    InvalidChallenge(String, String),
+    // the move log given with --replay and the line that could not be read
+    BadLog(PathBuf, usize)
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::Io(e) => write!(f, "i/o error: {e}"),
+            GameError::CorruptSave(path) => write!(f, "save file {} is corrupt", path.display()),
+            GameError::BadArgs(msg) => write!(f, "{msg}, see --help for usage"),
+            GameError::InvalidBoard(msg) => write!(f, "invalid board: {msg}"),
+            GameError::BadPrefs(path, line) => write!(f, "could not read `{line}` in {}", path.display()),
+            GameError::IsDirectory(path) => write!(f, "{} is a directory, the game keeps a file there, move it out of the way", path.display()),
+            GameError::InvalidChallenge(code, reason) => write!(f, "`{code}` is not a challenge code, {reason}"),
+            GameError::BadLog(path, line) => write!(f, "line {line} of {} is not a move log entry", path.display())
+        }
+    }
+}
+
+impl std::error::Error for GameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for GameError {
+    fn from(e: io::Error) -> Self {
+        GameError::Io(e)
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+use std::io::{stdout, Write};
+
+// what the game reports besides drawing, so a sound backend can be added without touching the
+// move logic. every hook does nothing unless it is overridden
+pub trait Feedback: fmt::Debug {
+    // the tiles the last move made, one per merge
+    fn on_merge(&mut self, _tiles: &[u64]) {}
+    fn on_spawn(&mut self, _tile: u64) {}
+    fn on_gameover(&mut self, _score: u64) {}
+}
+
+// the default, the game stays silent
+#[derive(Debug, Default)]
+pub struct Silent;
+
+impl Feedback for Silent {}
+
+// rings the terminal bell on merges and when the game is lost, picked with --bell
+#[derive(Debug, Default)]
+pub struct Bell;
+
+impl Bell {
+    fn ring(&self) {
+        // a bell that does not ring is not worth ending the game over
+        let mut out = stdout();
+        let _ = out.write_all(b"\x07").and_then(|_| out.flush());
+    }
+}
+
+impl Feedback for Bell {
+    fn on_merge(&mut self, _tiles: &[u64]) {
+        self.ring();
+    }
+
+    fn on_gameover(&mut self, _score: u64) {
+        self.ring();
+    }
+}
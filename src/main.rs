@@ -1,8 +1,13 @@
-use app::App;
-use color_eyre::Result;
+use app::{check_start_tiles, find_desync, verify_replay, AppConfig, Direction, Game, GameMode, Rule, MAX_SIZE, MIN_SIZE, START_TILES, ASPECT};
+use color_eyre::{eyre::{eyre, WrapErr}, Result};
+use challenge::decode_challenge;
+use errors::GameError;
 
 use std::fs::File;
 use std::env;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use read_write::*;
 
@@ -10,32 +15,411 @@ pub mod errors;
 pub mod tui;
 pub mod app;
 pub mod read_write;
+pub mod bot;
+pub mod feedback;
+pub mod challenge;
+
+const USAGE: &str = "\
+usage: game_2048 [options]
+
+options:
+    --autorepeat    holding an arrow key keeps moving
+    --ascii         draw the board with plain ascii and no colors
+    --size <n>      fields per row and column, 3 to 8 (default the last size played)
+    --rows <n>      rows of a board that is not square, 3 to 8 (default --size),
+                    boards that are not square keep no highscore
+    --cols <n>      columns of a board that is not square, 3 to 8 (default --size)
+    --difficulty <easy|normal|hard>
+                    how often new pieces are 4s or 8s instead of 2s (default normal)
+    --blockers <n>  block n random cells in every game, at most half the board
+    --start-tiles <n>
+                    tiles a new board starts with, at least 1 and at most the free cells (default 2)
+    --rule <classic|fib|threes>
+                    how tiles merge: equal tiles double, neighbours in 1, 2, 3, 5, 8, .. add up,
+                    or 1 and 2 make 3 and equal tiles from 3 up double (default classic).
+                    the target moves along, other rules keep no highscore
+    --practice      set up the board by hand before playing, practice games keep no highscore
+    --load <file>   play from the board in a JSON file like {\"size\":4,\"board\":[[2,0,0,0],..]},
+                    games on a loaded board keep no highscore
+    --margin <n>    rows around the board, at least 2, twice as many columns at the sides (default 2)
+    --zen           hide the score and never end the game, cannot be combined with a mode
+    --hide-dead-board
+                    hide the board once the game is lost instead of showing it dimmed
+    --exponent      show each tile as its exponent, 11 instead of 2048
+    --mini          draw each tile as its exponent in a board without borders, 1 for 2 up to 11
+                    for 2048, which is also used when the terminal is too small for the full board
+    --merge-preview mark the tiles that would merge with an arrow for each direction
+    --fps <n>       draw at most n frames per second (default 60)
+    --auto-resume   the game pauses when the terminal loses focus,
+                    with this it goes on once the focus is back
+    --bell          ring the terminal bell on merges and when the game is lost
+    --anim-speed <x>
+                    speed up the tile highlights, flashes and the score counter x times, 0.1 to 10 (default 1)
+    --aspect <x>    columns per row of every cell, 0.5 to 4, or 0 to stretch the cells over
+                    the whole terminal (default 2, square on most terminals)
+    --reduced-motion
+                    no animations or flashes, every change shows at once
+    --seed <n>      seed the tile sequence, the same seed plays the same game
+    --challenge <code>
+                    play the game someone shared with x, the seed and the mode are in the code.
+                    the board size, difficulty and rule are not, play with the same ones
+    --daily         play today's challenge, the same board for everyone, cannot be combined with --seed
+    --time-attack <seconds>
+                    score as much as possible before the time runs out
+    --move-limit <n>
+                    score as much as possible in n moves
+    --target <n>    the tile that wins, a power of two of at least 8 (default 2048)
+    --undos <n>     how many moves can be undone per game (default unlimited)
+    --swaps <n>     how many times per game s lets two fields trade places (default 0),
+                    games with a swap or an undo keep no hardcore highscore
+    --bot <file>    no screen, read moves from file, a named pipe or - for stdin, one per line
+                    as up, right, down, left or their first letter, quit ends the game.
+                    after every move the state is written to stdout as a line of JSON:
+                    {\"score\":0,\"moved\":false,\"over\":false,\"board\":[[2,0,0,0],..]}
+                    uses --seed, --size, --rows, --cols, --difficulty, --blockers and --start-tiles, nothing else
+    --serve <port>  like --bot, but over TCP on 127.0.0.1:port, one client at a time
+    --replay <file> no screen, play a --log of one game again and check every board against its hash,
+                    prints the final score and board when all of them match.
+                    needs the --seed of the game and the --size, --rows, --cols, --difficulty, --blockers
+                    and --start-tiles it was played with, on the classic rule without undos or swaps
+    --log <file>    append every move, the resulting board and its hash to file as JSON lines
+    --autosave      write the game to Autosave.json next to Highscore.bin after every move
+    --resume        go on with the game in Autosave.json after a crash, as the same game with its
+                    score, moves, mode and difficulty, and keep autosaving it
+    --no-save       never read or write Highscore.bin and never write Preferences.txt,
+                    highscores only last until the game exits, cannot be combined with --autosave or --resume
+    -h, --help      print this help and exit
+    -V, --version   print the version and exit
+
+the defaults of --size, --difficulty, --ascii, --autorepeat, --merge-preview, --margin
+and --fps are read from Preferences.txt next to Highscore.bin
+";
 
 fn main() -> Result<()> {
+    let mut autorepeat = None;
+    let mut ascii = None;
+    let mut seed = None;
+    let mut challenge = None;
+    let mut size = None;
+    let mut rows = None;
+    let mut cols = None;
+    let mut blockers = 0;
+    let mut start_tiles = START_TILES;
+    let mut rule = Rule::Classic;
+    let mut margin = None;
+    let mut practice = false;
+    let mut zen = false;
+    let mut fps = None;
+    let mut anim_speed = 1.0;
+    let mut reduced_motion = false;
+    let mut aspect = ASPECT;
+    let mut bell = false;
+    let mut auto_resume = false;
+    let mut merge_preview = None;
+    let mut mini = false;
+    let mut exponent = false;
+    let mut hide_dead_board = false;
+    let mut mode = GameMode::Classic;
+    let mut difficulty = None;
+    let mut target: u64 = 2048;
+    let mut undos = None;
+    let mut swaps = 0;
+    let mut log = None;
+    let mut no_save = false;
+    // labels the cells with their indices, for working on the game and left out of the usage
+    let mut debug = false;
+    let mut autosave = false;
+    let mut resume = false;
+    let mut load = None;
+    let mut bot = None;
+    let mut replay = None;
+    let mut serve = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            "-V" | "--version" => {
+                println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+                return Ok(());
+            }
+            "--autorepeat" => autorepeat = Some(true),
+            "--ascii" => ascii = Some(true),
+            "--size" => size = Some(parse_size(&arg, args.next())?),
+            "--rows" => rows = Some(parse_size(&arg, args.next())?),
+            "--cols" => cols = Some(parse_size(&arg, args.next())?),
+            "--difficulty" => difficulty = Some(parse_value(&arg, args.next())?),
+            "--blockers" => blockers = parse_value(&arg, args.next())?,
+            "--start-tiles" => start_tiles = parse_value(&arg, args.next())?,
+            "--rule" => rule = parse_value(&arg, args.next())?,
+            "--practice" => practice = true,
+            "--margin" => margin = Some(parse_value(&arg, args.next())?),
+            "--zen" => zen = true,
+            "--hide-dead-board" => hide_dead_board = true,
+            "--exponent" => exponent = true,
+            "--mini" => mini = true,
+            "--merge-preview" => merge_preview = Some(true),
+            "--fps" => fps = Some(parse_value(&arg, args.next())?),
+            "--anim-speed" => anim_speed = parse_value(&arg, args.next())?,
+            "--aspect" => aspect = parse_value(&arg, args.next())?,
+            "--reduced-motion" => reduced_motion = true,
+            "--bell" => bell = true,
+            "--auto-resume" => auto_resume = true,
+            "--seed" => seed = Some(parse_value(&arg, args.next())?),
+            "--challenge" => challenge = Some(decode_challenge(&parse_value::<String>(&arg, args.next())?)?),
+            "--daily" => set_mode(&mut mode, GameMode::Daily)?,
+            "--target" => target = parse_value(&arg, args.next())?,
+            "--load" => load = Some(parse_value::<PathBuf>(&arg, args.next())?),
+            "--serve" => serve = Some(parse_value(&arg, args.next())?),
+            "--bot" => bot = Some(parse_value::<PathBuf>(&arg, args.next())?),
+            "--replay" => replay = Some(parse_value::<PathBuf>(&arg, args.next())?),
+            "--log" => log = Some(parse_value::<PathBuf>(&arg, args.next())?),
+            "--no-save" => no_save = true,
+            "--autosave" => autosave = true,
+            "--resume" => resume = true,
+            "--debug" => debug = true,
+            "--undos" => undos = Some(parse_value(&arg, args.next())?),
+            "--swaps" => swaps = parse_value(&arg, args.next())?,
+            "--move-limit" => set_mode(&mut mode, GameMode::MoveLimit(parse_positive(&arg, args.next())?))?,
+            "--time-attack" => set_mode(&mut mode, GameMode::TimeAttack(parse_positive(&arg, args.next())?))?,
+            _ => return Err(GameError::BadArgs(format!("unknown option `{arg}`")).into())
+        }
+    }
+
+    if let Some((challenge_seed, challenge_mode)) = challenge {
+        if seed.is_some() {
+            return Err(GameError::BadArgs("--challenge cannot be combined with --seed".to_string()).into());
+        }
+        seed = Some(challenge_seed);
+        set_mode(&mut mode, challenge_mode)?;
+    }
+    // the daily game is seeded by the date
+    if mode == GameMode::Daily && seed.is_some() {
+        return Err(GameError::BadArgs("--daily cannot be combined with --seed".to_string()).into());
+    }
+    if zen && mode != GameMode::Classic {
+        return Err(GameError::BadArgs("--zen cannot be combined with --daily, --time-attack or --move-limit".to_string()).into());
+    }
+    // boards set up by hand and read back from a file only hold powers of two
+    if rule != Rule::Classic && (practice || load.is_some() || autosave || resume) {
+        return Err(GameError::BadArgs("--rule cannot be combined with --practice, --load, --autosave or --resume".to_string()).into());
+    }
+    // the autosaved game brings its own board and mode
+    if resume && (practice || zen || load.is_some() || mode != GameMode::Classic) {
+        return Err(GameError::BadArgs("--resume cannot be combined with --practice, --zen, --load or another mode".to_string()).into());
+    }
+    // a resumed game goes on being autosaved
+    autosave |= resume;
+    // --no-save promises nothing is written next to the game
+    if autosave && no_save {
+        return Err(GameError::BadArgs("--autosave and --resume cannot be combined with --no-save".to_string()).into());
+    }
+
+    // before the hooks, they write terminal escapes that would end up in the bot's input
+    if let Some(input) = bot {
+        let size = size.unwrap_or(4);
+        let (rows, cols) = (rows.unwrap_or(size), cols.unwrap_or(size));
+        check_start_tiles(start_tiles, rows, cols, blockers)?;
+        let mut game = Game::new(seed, rows, cols, difficulty.unwrap_or_default(), blockers, start_tiles);
+        if input.as_os_str() == "-" {
+            bot::play(io::stdin().lock(), io::stdout().lock(), &mut game)?;
+        }
+        else {
+            let file = File::open(&input).wrap_err_with(|| format!("could not open {}", input.display()))?;
+            bot::play(BufReader::new(file), io::stdout().lock(), &mut game)?;
+        }
+        return Ok(());
+    }
+    if let Some(port) = serve {
+        let difficulty = difficulty.unwrap_or_default();
+        let size = size.unwrap_or(4);
+        let (rows, cols) = (rows.unwrap_or(size), cols.unwrap_or(size));
+        check_start_tiles(start_tiles, rows, cols, blockers)?;
+        bot::serve(port, || Game::new(seed, rows, cols, difficulty, blockers, start_tiles))
+            .wrap_err_with(|| format!("could not serve on port {port}"))?;
+        return Ok(());
+    }
+    if let Some(replay) = replay {
+        let Some(seed) = seed else {
+            return Err(GameError::BadArgs("--replay needs the --seed the game was played with".to_string()).into());
+        };
+        // Game only knows the classic rule
+        if rule != Rule::Classic {
+            return Err(GameError::BadArgs("--replay cannot be combined with --rule".to_string()).into());
+        }
+        let difficulty = difficulty.unwrap_or_default();
+        let size = size.unwrap_or(4);
+        let (rows, cols) = (rows.unwrap_or(size), cols.unwrap_or(size));
+        check_start_tiles(start_tiles, rows, cols, blockers)?;
+        let new_game = || Game::new(Some(seed), rows, cols, difficulty, blockers, start_tiles);
+        let log = read_log(&replay).wrap_err_with(|| format!("could not read the move log {}", replay.display()))?;
+        if let Some(i) = find_desync(new_game(), &log) {
+            return Err(eyre!("move {} of {} ({}) does not give the logged board", i + 1, replay.display(), log[i].0));
+        }
+        let moves: Vec<Direction> = log.iter().map(|(direction, _)| *direction).collect();
+        let (score, grid) = verify_replay(new_game(), &moves);
+        println!("all {} moves match, final score {score}", log.len());
+        print!("{grid}");
+        return Ok(());
+    }
+
     errors::install_hooks()?;
-    let mut terminal = tui::init()?;
-    let path_to_self = env::current_exe()?;
-    let path = path_to_self
-        .parent()
-        .and_then(|p| p.parent())
-        .and_then(|p|p.parent())
-        .map(|p|p.join("Highscore.bin"))
-        .unwrap();
-    let number: u64;
-    if !path.exists() {
+    let prefs_path = prefs_path()?;
+    let prefs = if !prefs_path.exists() {
+        // written once so there is a file to edit
+        if !no_save {
+            save_prefs(&prefs_path, &Prefs::default()).wrap_err("could not create the preferences")?;
+        }
+        Prefs::default()
+    }
+    else {
+        load_prefs(&prefs_path).wrap_err("could not load the preferences")?
+    };
+    let path = save_path()?;
+    let mut records = if no_save {
+        Records::default()
+    }
+    else if !path.exists() {
         File::create(&path)?;
-        number = 0;
+        Records::default()
     }
     else {
-        number = read(&path)?;
+        read_records(&path).wrap_err("could not load the highscore")?
+    };
+
+    let resume = if resume { Some(load_game(&autosave_path()?).wrap_err("could not resume the autosaved game")?) } else { None };
+    if let Some(saved) = resume.as_ref().filter(|saved| saved.mode == GameMode::Daily) {
+        if saved.day != today() {
+            return Err(eyre!("the autosaved daily game was played on another day"));
+        }
+        mode = GameMode::Daily;
+    }
+    if mode == GameMode::Daily {
+        seed = Some(today());
+        // the stored daily highscore belongs to an earlier day
+        if records.daily_date != today() {
+            records.daily_date = today();
+            records.highscores.retain(|(mode, _, _), _| *mode != GameMode::Daily);
+            records.hardcore.retain(|(mode, _, _), _| *mode != GameMode::Daily);
+        }
+    }
+    let last_size = Some(records.last_size as usize).filter(|n| (MIN_SIZE..=MAX_SIZE).contains(n));
+    let size = size.or(prefs.size).or(last_size).unwrap_or(4);
+    let (rows, cols) = (rows.unwrap_or(size), cols.unwrap_or(size));
+    let load = match load {
+        Some(load) => Some(load_state(&load).wrap_err_with(|| format!("could not load the board from {}", load.display()))?),
+        None => None
+    };
+    let log = match log {
+        Some(log) => Some(MoveLog::open(&log).wrap_err("could not open the move log")?),
+        None => None
+    };
+    let config = AppConfig {
+        seed,
+        size: if rows == cols { rows } else { size },
+        shape: (rows != cols).then_some((rows, cols)),
+        records,
+        mode,
+        difficulty: difficulty.unwrap_or(prefs.difficulty),
+        rule,
+        target,
+        blockers,
+        start_tiles,
+        undo_limit: undos,
+        swap_limit: swaps,
+        practice,
+        zen,
+        no_save,
+        autorepeat: autorepeat.unwrap_or(prefs.autorepeat),
+        ascii: ascii.unwrap_or(prefs.ascii),
+        margin: margin.unwrap_or(prefs.margin),
+        fps: fps.unwrap_or(prefs.fps),
+        merge_preview: merge_preview.unwrap_or(prefs.merge_preview),
+        anim_speed,
+        reduced_motion,
+        aspect,
+        mini,
+        exponent,
+        hide_dead_board,
+        debug,
+        auto_resume,
+        feedback: if bell { Box::new(feedback::Bell) } else { Box::new(feedback::Silent) },
+        log,
+        autosave: if autosave { Some(autosave_path()?) } else { None },
+        load,
+        resume,
+        prefs
+    };
+    let mut app = config.build()?;
+
+    let mut terminal = tui::init()?;
+    // restore before looking at the result so an error never leaves the terminal in raw mode
+    // and report the game's error over a failed restore, it is the one that explains what went wrong
+    let result = app.run(&mut terminal);
+    let restored = tui::restore();
+    result?;
+    restored?;
+
+    if let Some(log) = app.log.as_mut() {
+        log.flush().wrap_err("could not write the move log")?;
+    }
+    if !no_save {
+        save_records(&path, &app.records)
+            .wrap_err("could not save the highscore")?;
     }
+    Ok(())
+}
+
 
-    let mut app = App::new()?;
-    app.highscore = number;
-    app.run(&mut terminal)?;
-    tui::restore()?;
-    
-    save(&path, app.highscore)?;
+fn set_mode(mode: &mut GameMode, new: GameMode) -> Result<(), GameError> {
+    if *mode != GameMode::Classic {
+        return Err(GameError::BadArgs("only one of --daily, --time-attack, --move-limit and a timed or limited --challenge can be used".to_string()));
+    }
+    *mode = new;
     Ok(())
 }
 
+fn parse_size(flag: &str, value: Option<String>) -> Result<usize, GameError> {
+    let n = parse_value(flag, value)?;
+    if !(MIN_SIZE..=MAX_SIZE).contains(&n) {
+        return Err(GameError::BadArgs(format!("`{flag}` must be between {MIN_SIZE} and {MAX_SIZE}, got {n}")));
+    }
+    Ok(n)
+}
+
+// a game with no seconds or no moves would be over before it started
+fn parse_positive(flag: &str, value: Option<String>) -> Result<u64, GameError> {
+    let n = parse_value(flag, value)?;
+    if n == 0 {
+        return Err(GameError::BadArgs(format!("`{flag}` must be at least 1")));
+    }
+    Ok(n)
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, GameError> {
+    let value = value.ok_or_else(|| GameError::BadArgs(format!("`{flag}` needs a value")))?;
+    value
+        .parse()
+        .map_err(|_| GameError::BadArgs(format!("invalid value `{value}` for `{flag}`")))
+}
+
+// the current UTC date as yyyymmdd
+fn today() -> u64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year * 10000 + month * 100 + day) as u64
+}
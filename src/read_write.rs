@@ -1,17 +1,639 @@
+use std::collections::BTreeMap;
+use std::env;
 use std::io;
 use std::io::prelude::*;
-use std::path::PathBuf;
-use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::io::BufWriter;
+use std::process::{Command, Stdio};
 
-pub fn save(path: &PathBuf, number: u64) -> io::Result<()> {
+use crate::app::{Difficulty, Direction, GameMode, Grid, MAX_SIZE, MIN_SIZE};
+use crate::errors::GameError;
+
+// entries written between flushes of the move log
+const LOG_FLUSH: u64 = 16;
+
+// tags of the modes in the (tag, parameter, size, highscore) entries
+const CLASSIC: u64 = 0;
+const TIME_ATTACK: u64 = 1;
+const MOVE_LIMIT: u64 = 2;
+const DAILY: u64 = 3;
+// added to the tag once per step the difficulty is away from normal, easy is one step and hard two
+const DIFFICULTY_STEP: u64 = 4;
+// added to the tag of a hardcore entry, after the three difficulties
+const HARDCORE: u64 = 3 * DIFFICULTY_STEP;
+
+// everything kept in Highscore.bin, stored as consecutive little endian u64s:
+// classic 4x4 highscore, best tile, daily date, daily 4x4 highscore, last board size,
+// then one (tag, parameter, size, highscore) entry for every other mode, board size and difficulty,
+// the two highscores in the header are the ones played on normal.
+// hardcore highscores all go in tagged entries, they only count games where nothing was undone
+#[derive(Debug, Default, Clone)]
+pub struct Records {
+    pub best_tile: u64,
+    // the daily challenge the daily highscores belong to, as yyyymmdd
+    pub daily_date: u64,
+    // board size picked in the menu last time, 0 if none was picked yet
+    pub last_size: u64,
+    pub highscores: BTreeMap<(GameMode, usize, Difficulty), u64>,
+    pub hardcore: BTreeMap<(GameMode, usize, Difficulty), u64>
+}
+
+impl Records {
+    pub fn highscore(&self, mode: GameMode, size: usize, difficulty: Difficulty) -> u64 {
+        self.highscores.get(&(mode, size, difficulty)).copied().unwrap_or(0)
+    }
+
+    pub fn hardcore_highscore(&self, mode: GameMode, size: usize, difficulty: Difficulty) -> u64 {
+        self.hardcore.get(&(mode, size, difficulty)).copied().unwrap_or(0)
+    }
+}
+
+// Highscore.bin sits in the project root, next to the target directory holding the binary
+pub fn save_path() -> Result<PathBuf, GameError> {
+    let path_to_self = env::current_exe()?;
+    path_to_self
+        .parent()
+        .and_then(|p| p.parent())
+        .and_then(|p| p.parent())
+        .map(|p| p.join("Highscore.bin"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no directory to keep Highscore.bin in").into())
+}
+
+// the preferences sit next to Highscore.bin
+pub fn prefs_path() -> Result<PathBuf, GameError> {
+    Ok(save_path()?.with_file_name("Preferences.txt"))
+}
+
+// where --autosave keeps the running game, --load picks it up again
+pub fn autosave_path() -> Result<PathBuf, GameError> {
+    Ok(save_path()?.with_file_name("Autosave.json"))
+}
+
+// defaults for the options that change how the game looks and feels,
+// the command line overrides them for a single run
+#[derive(Debug, Clone)]
+pub struct Prefs {
+    // None plays the size picked in the menu last time
+    pub size: Option<usize>,
+    pub difficulty: Difficulty,
+    pub ascii: bool,
+    pub autorepeat: bool,
+    pub merge_preview: bool,
+    pub margin: u16,
+    pub fps: u32
+}
+
+impl Default for Prefs {
+    fn default() -> Self {
+        Prefs {
+            size: None,
+            difficulty: Difficulty::Normal,
+            ascii: false,
+            autorepeat: false,
+            merge_preview: false,
+            margin: 2,
+            fps: 60
+        }
+    }
+}
+
+// one `key = value` per line, lines starting with # are comments
+pub fn save_prefs(path: &Path, prefs: &Prefs) -> Result<(), GameError> {
+    not_a_directory(path)?;
     let mut file = File::create(path)?;
-    file.write_all(&number.to_le_bytes())?;
+    writeln!(file, "# defaults for game_2048, command line options override them for one game")?;
+    match prefs.size {
+        Some(size) => writeln!(file, "size = {size}")?,
+        None => writeln!(file, "size = last")?
+    }
+    writeln!(file, "difficulty = {}", prefs.difficulty)?;
+    writeln!(file, "ascii = {}", prefs.ascii)?;
+    writeln!(file, "autorepeat = {}", prefs.autorepeat)?;
+    writeln!(file, "merge_preview = {}", prefs.merge_preview)?;
+    writeln!(file, "margin = {}", prefs.margin)?;
+    writeln!(file, "fps = {}", prefs.fps)?;
+    Ok(())
+}
+
+// missing keys keep their defaults, anything that does not parse is an error
+pub fn load_prefs(path: &Path) -> Result<Prefs, GameError> {
+    not_a_directory(path)?;
+    let mut prefs = Prefs::default();
+    for line in fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = || GameError::BadPrefs(path.to_path_buf(), line.to_string());
+        let (key, value) = line.split_once('=').ok_or_else(bad)?;
+        let value = value.trim();
+        match key.trim() {
+            "size" if value == "last" => prefs.size = None,
+            "size" => {
+                let size = value.parse().map_err(|_| bad())?;
+                if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+                    return Err(bad());
+                }
+                prefs.size = Some(size);
+            }
+            "difficulty" => prefs.difficulty = value.parse().map_err(|_| bad())?,
+            "ascii" => prefs.ascii = value.parse().map_err(|_| bad())?,
+            "autorepeat" => prefs.autorepeat = value.parse().map_err(|_| bad())?,
+            "merge_preview" => prefs.merge_preview = value.parse().map_err(|_| bad())?,
+            "margin" => prefs.margin = value.parse().map_err(|_| bad())?,
+            "fps" => prefs.fps = value.parse().ok().filter(|fps| *fps > 0).ok_or_else(bad)?,
+            _ => return Err(bad())
+        }
+    }
+    Ok(prefs)
+}
+
+// writes the format described at Records next to the target and renames over it,
+// so a crash mid write leaves the old file intact
+pub fn save_records(path: &Path, records: &Records) -> Result<(), GameError> {
+    not_a_directory(path)?;
+    let temp = temp_path(path);
+    let mut file = File::create(&temp)?;
+    let mut numbers = vec![
+        records.highscore(GameMode::Classic, 4, Difficulty::Normal),
+        records.best_tile,
+        records.daily_date,
+        records.highscore(GameMode::Daily, 4, Difficulty::Normal),
+        records.last_size
+    ];
+    let entries = records.highscores.iter().map(|entry| (entry, 0)).chain(records.hardcore.iter().map(|entry| (entry, HARDCORE)));
+    for (((mode, size, difficulty), highscore), board) in entries {
+        let in_header = board == 0 && *size == 4 && *difficulty == Difficulty::Normal;
+        let (tag, parameter) = match mode {
+            GameMode::Classic | GameMode::Daily if in_header => continue,
+            GameMode::Classic => (CLASSIC, 0),
+            GameMode::TimeAttack(secs) => (TIME_ATTACK, *secs),
+            GameMode::MoveLimit(moves) => (MOVE_LIMIT, *moves),
+            GameMode::Daily => (DAILY, 0)
+        };
+        let steps = match difficulty {
+            Difficulty::Normal => 0,
+            Difficulty::Easy => 1,
+            Difficulty::Hard => 2
+        };
+        numbers.extend([board + tag + steps * DIFFICULTY_STEP, parameter, *size as u64, *highscore]);
+    }
+    for number in numbers {
+        file.write_all(&number.to_le_bytes())?;
+    }
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+// the inverse of save_records. an empty file, like the one created on the first start, holds no records yet,
+// any other length that is not a whole number of entries is a CorruptSave
+pub fn read_records(path: &Path) -> Result<Records, GameError> {
+    not_a_directory(path)?;
+    // left behind by a save that was interrupted before the rename
+    let temp = temp_path(path);
+    if temp.exists() {
+        fs::remove_file(temp)?;
+    }
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+    if bytes.is_empty() {
+        return Ok(Records::default());
+    }
+    if !bytes.len().is_multiple_of(8) {
+        return Err(GameError::CorruptSave(path.to_path_buf()));
+    }
+    // older versions wrote fewer numbers, whatever is missing starts at 0
+    let mut numbers = bytes
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()));
+    let mut next = || numbers.next().unwrap_or(0);
+    let classic = next();
+    let best_tile = next();
+    let daily_date = next();
+    let daily = next();
+    let last_size = next();
+    let mut highscores = BTreeMap::from([
+        ((GameMode::Classic, 4, Difficulty::Normal), classic),
+        ((GameMode::Daily, 4, Difficulty::Normal), daily)
+    ]);
+    let rest: Vec<u64> = numbers.collect();
+    if !rest.len().is_multiple_of(4) {
+        return Err(GameError::CorruptSave(path.to_path_buf()));
+    }
+    let mut hardcore = BTreeMap::new();
+    for entry in rest.chunks_exact(4) {
+        let (board, tag) = if entry[0] >= HARDCORE { (&mut hardcore, entry[0] - HARDCORE) } else { (&mut highscores, entry[0]) };
+        let difficulty = match tag / DIFFICULTY_STEP {
+            0 => Difficulty::Normal,
+            1 => Difficulty::Easy,
+            2 => Difficulty::Hard,
+            _ => return Err(GameError::CorruptSave(path.to_path_buf()))
+        };
+        let mode = match tag % DIFFICULTY_STEP {
+            CLASSIC => GameMode::Classic,
+            TIME_ATTACK => GameMode::TimeAttack(entry[1]),
+            MOVE_LIMIT => GameMode::MoveLimit(entry[1]),
+            DAILY => GameMode::Daily,
+            _ => unreachable!()
+        };
+        board.insert((mode, entry[2] as usize, difficulty), entry[3]);
+    }
+    Ok(Records {
+        best_tile,
+        daily_date,
+        last_size,
+        highscores,
+        hardcore
+    })
+}
+
+// opening a directory fails with an error that does not say which path is wrong
+fn not_a_directory(path: &Path) -> Result<(), GameError> {
+    if path.is_dir() {
+        return Err(GameError::IsDirectory(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+// appends one JSON object per move to the file given with --log
+#[derive(Debug)]
+pub struct MoveLog {
+    writer: BufWriter<File>,
+    entries: u64
+}
+
+impl MoveLog {
+    pub fn open(path: &Path) -> Result<Self, GameError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(MoveLog {
+            writer: BufWriter::new(file),
+            entries: 0
+        })
+    }
+
+    pub fn record(&mut self, direction: Direction, score_delta: u64, merges: usize, board: &[Vec<u64>], hash: u64) -> Result<(), GameError> {
+        writeln!(
+            self.writer,
+            "{{\"direction\":\"{direction}\",\"score_delta\":{score_delta},\"merges\":{merges},\"board\":{},\"hash\":\"{hash:016x}\"}}",
+            board_json(board)
+        )?;
+        self.entries += 1;
+        // flushing now and then keeps most of the log if the game crashes
+        if self.entries.is_multiple_of(LOG_FLUSH) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), GameError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// the direction and hash of every line MoveLog wrote, in order
+pub fn read_log(path: &Path) -> Result<Vec<(Direction, u64)>, GameError> {
+    let text = fs::read_to_string(path)?;
+    let mut log = vec![];
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let bad = || GameError::BadLog(path.to_path_buf(), i + 1);
+        let string = |key| json_value(line, key).and_then(|value| value.strip_prefix('"')?.split('"').next()).ok_or_else(bad);
+        let direction = Direction::from_name(string("direction")?).ok_or_else(bad)?;
+        let hash = u64::from_str_radix(string("hash")?, 16).map_err(|_| bad())?;
+        log.push((direction, hash));
+    }
+    Ok(log)
+}
+
+// rows of tile values as a JSON array of arrays
+pub fn board_json(board: &[Vec<u64>]) -> String {
+    let rows: Vec<String> = board
+        .iter()
+        .map(|row| {
+            let vals: Vec<String> = row.iter().map(|val| val.to_string()).collect();
+            format!("[{}]", vals.join(","))
+        })
+        .collect();
+    format!("[{}]", rows.join(","))
+}
+
+// the format load_state reads back, the score is only there for whoever looks at the file.
+// a board that is not square has "rows" and "cols" instead of "size"
+pub fn save_state(path: &Path, board: &[Vec<u64>], score: u64) -> Result<(), GameError> {
+    write_atomically(path, &state_json(board, score, ""))
+}
+
+fn state_json(board: &[Vec<u64>], score: u64, rest: &str) -> String {
+    let (rows, cols) = (board.len(), board.first().map_or(0, |row| row.len()));
+    let size = if rows == cols { format!("\"size\":{rows}") } else { format!("\"rows\":{rows},\"cols\":{cols}") };
+    format!("{{{size},\"score\":{score},\"board\":{}{rest}}}", board_json(board))
+}
+
+// written next to the target and renamed over it like the records
+fn write_atomically(path: &Path, line: &str) -> Result<(), GameError> {
+    not_a_directory(path)?;
+    let temp = temp_path(path);
+    let mut file = File::create(&temp)?;
+    writeln!(file, "{line}")?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
     Ok(())
 }
 
-pub fn read(path: &PathBuf) -> io::Result<u64> {
-    let mut file = File::open(path)?;
-    let mut buffer = [0u8; 8];
-    file.read_exact(&mut buffer)?;
-    Ok(u64::from_le_bytes(buffer))
-}
\ No newline at end of file
+// everything --autosave keeps of a game so --resume can go on with it as the same game.
+// the file is a board like save_state writes with the rest after it, --load reads it as just the board
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedGame {
+    pub board: Vec<Vec<u64>>,
+    // indices of the blocked fields, they hold 0 in the board
+    pub blocked: Vec<usize>,
+    pub score: u64,
+    pub moves: u64,
+    pub mode: GameMode,
+    // yyyymmdd of a daily game, 0 for every other mode
+    pub day: u64,
+    pub difficulty: Difficulty,
+    pub seed: u64,
+    pub played_secs: u64,
+    pub undos_used: u64,
+    pub swaps_used: u64,
+    // the target was reached and the player went on
+    pub continued: bool,
+    // games set up by hand and zen games keep no highscores, resumed or not
+    pub set_up: bool,
+    pub zen: bool
+}
+
+// rewritten after every move by --autosave
+pub fn save_game(path: &Path, game: &SavedGame) -> Result<(), GameError> {
+    let (mode, limit) = match game.mode {
+        GameMode::Classic => ("classic", 0),
+        GameMode::Daily => ("daily", 0),
+        GameMode::TimeAttack(secs) => ("time-attack", secs),
+        GameMode::MoveLimit(moves) => ("move-limit", moves)
+    };
+    let blocked: Vec<String> = game.blocked.iter().map(|i| i.to_string()).collect();
+    let rest = format!(
+        ",\"blocked\":[{}],\"moves\":{},\"mode\":\"{mode}\",\"limit\":{limit},\"day\":{},\"difficulty\":\"{}\",\"seed\":{},\"played\":{},\"undos\":{},\"swaps\":{},\"continued\":{},\"set_up\":{},\"zen\":{}",
+        blocked.join(","), game.moves, game.day, game.difficulty, game.seed, game.played_secs, game.undos_used, game.swaps_used, game.continued, game.set_up, game.zen
+    );
+    write_atomically(path, &state_json(&game.board, game.score, &rest))
+}
+
+// the inverse of save_game, a file without the game around the board is not an autosave
+pub fn load_game(path: &Path) -> Result<SavedGame, GameError> {
+    let board = load_state(path)?.values();
+    let text = fs::read_to_string(path)?;
+    let missing = |key: &str| GameError::InvalidBoard(format!("no \"{key}\" in the file, it is not an autosave"));
+    let number = |key: &str| json_number::<u64>(&text, key)?.ok_or_else(|| missing(key));
+    let string = |key: &str| json_value(&text, key)
+        .and_then(|value| value.strip_prefix('"')?.split('"').next())
+        .ok_or_else(|| missing(key));
+    let flag = |key: &str| json_value(&text, key).map(|value| value.starts_with("true")).ok_or_else(|| missing(key));
+    let blocked = json_value(&text, "blocked")
+        .and_then(|value| value.strip_prefix('[')?.split(']').next())
+        .ok_or_else(|| missing("blocked"))?;
+    let blocked = blocked
+        .split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(|i| i.parse().map_err(|_| GameError::InvalidBoard(format!("blocked field `{i}` is not a number"))))
+        .collect::<Result<Vec<usize>, _>>()?;
+    let mode = match string("mode")? {
+        "classic" => GameMode::Classic,
+        "daily" => GameMode::Daily,
+        "time-attack" => GameMode::TimeAttack(number("limit")?),
+        "move-limit" => GameMode::MoveLimit(number("limit")?),
+        mode => return Err(GameError::InvalidBoard(format!("unknown mode `{mode}`")))
+    };
+    Ok(SavedGame {
+        board,
+        blocked,
+        score: number("score")?,
+        moves: number("moves")?,
+        mode,
+        day: number("day")?,
+        difficulty: string("difficulty")?.parse().map_err(|_| GameError::InvalidBoard("unknown difficulty".to_string()))?,
+        seed: number("seed")?,
+        played_secs: number("played")?,
+        undos_used: number("undos")?,
+        swaps_used: number("swaps")?,
+        continued: flag("continued")?,
+        set_up: flag("set_up")?,
+        zen: flag("zen")?
+    })
+}
+
+// reads a board saved as {"size":n,"board":[[..],..]}, a line of the move log works as well
+pub fn load_state(path: &Path) -> Result<Grid, GameError> {
+    let text = fs::read_to_string(path)?;
+    let board = json_value(&text, "board")
+        .ok_or_else(|| GameError::InvalidBoard("no \"board\" in the file".to_string()))?;
+    let values = parse_rows(board)?;
+    let (rows, cols) = (values.len(), values.first().map_or(0, |row| row.len()));
+    for (key, expected, what) in [("size", rows, "rows"), ("size", cols, "columns"), ("rows", rows, "rows"), ("cols", cols, "columns")] {
+        if let Some(n) = json_number::<usize>(&text, key)? {
+            if n != expected {
+                return Err(GameError::InvalidBoard(format!("\"{key}\" is {n} but the board has {expected} {what}")));
+            }
+        }
+    }
+    if !(MIN_SIZE..=MAX_SIZE).contains(&rows) || !(MIN_SIZE..=MAX_SIZE).contains(&cols) {
+        return Err(GameError::InvalidBoard(format!("the board must be {MIN_SIZE}x{MIN_SIZE} to {MAX_SIZE}x{MAX_SIZE}")));
+    }
+    Grid::from_values(&values)
+}
+
+// the number after "key":, None if there is no such key
+fn json_number<T: std::str::FromStr>(text: &str, key: &str) -> Result<Option<T>, GameError> {
+    let Some(value) = json_value(text, key) else {
+        return Ok(None);
+    };
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits
+        .parse()
+        .map(Some)
+        .map_err(|_| GameError::InvalidBoard(format!("\"{key}\" is not a number")))
+}
+
+// hands the text to the first clipboard tool that is installed, there is no clipboard crate to use
+pub fn copy_to_clipboard(text: &str) -> Result<(), GameError> {
+    let tools: [(&str, &[&str]); 5] = [
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+        ("clip", &[])
+    ];
+    for (tool, args) in tools {
+        let Ok(mut child) = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn() else {
+            continue;
+        };
+        // dropping stdin closes it, most tools only copy once their input ends
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "no clipboard tool found").into())
+}
+
+// whatever follows "key": in a JSON object
+fn json_value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let start = text.find(&format!("\"{key}\""))? + key.len() + 2;
+    text[start..].trim_start().strip_prefix(':').map(|rest| rest.trim_start())
+}
+
+// the inverse of board_json, stops after the closing bracket
+fn parse_rows(text: &str) -> Result<Vec<Vec<u64>>, GameError> {
+    let mut rows: Vec<Vec<u64>> = vec![];
+    let mut depth = 0;
+    let mut number = String::new();
+    let mut prev = ' ';
+    for c in text.chars() {
+        match c {
+            '[' if depth < 2 => {
+                depth += 1;
+                if depth == 2 {
+                    rows.push(vec![]);
+                }
+            }
+            ']' | ',' if depth > 0 => {
+                if !number.is_empty() {
+                    let val = number
+                        .parse()
+                        .map_err(|_| GameError::InvalidBoard(format!("{number} is too large")))?;
+                    rows.last_mut().unwrap().push(val);
+                    number.clear();
+                }
+                if c == ']' {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(rows);
+                    }
+                }
+            }
+            '0'..='9' if depth == 2 && (number.is_empty() || prev.is_ascii_digit()) => number.push(c),
+            c if c.is_whitespace() => {}
+            _ => return Err(GameError::InvalidBoard(format!("unexpected `{c}` in \"board\""))),
+        }
+        prev = c;
+    }
+    Err(GameError::InvalidBoard("\"board\" is not closed".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a directory of its own for every test, they run in parallel
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("game_2048-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn a_move_log_reads_back() {
+        let path = temp_dir("log").join("moves.log");
+        let mut log = MoveLog::open(&path).unwrap();
+        log.record(Direction::Left, 4, 1, &[vec![4, 0], vec![0, 2]], 0xdead_beef).unwrap();
+        log.record(Direction::Up, 0, 0, &[vec![4, 2], vec![0, 0]], u64::MAX).unwrap();
+        log.flush().unwrap();
+        assert_eq!(read_log(&path).unwrap(), vec![(Direction::Left, 0xdead_beef), (Direction::Up, u64::MAX)]);
+        fs::write(&path, "{\"direction\":\"left\",\"hash\":\"00ff\"}\n\n{\"direction\":\"sideways\",\"hash\":\"00ff\"}\n").unwrap();
+        assert!(matches!(read_log(&path), Err(GameError::BadLog(_, 3))));
+    }
+
+    #[test]
+    fn records_read_back_as_saved() {
+        let path = temp_dir("records").join("Highscore.bin");
+        let records = Records {
+            best_tile: 1024,
+            daily_date: 20261014,
+            last_size: 5,
+            highscores: BTreeMap::from([
+                ((GameMode::Classic, 4, Difficulty::Normal), 5000),
+                ((GameMode::Daily, 4, Difficulty::Normal), 300),
+                ((GameMode::Classic, 6, Difficulty::Normal), 9000),
+                ((GameMode::TimeAttack(60), 4, Difficulty::Hard), 800),
+                ((GameMode::MoveLimit(100), 5, Difficulty::Easy), 1200)
+            ]),
+            hardcore: BTreeMap::from([
+                ((GameMode::Classic, 4, Difficulty::Normal), 4000),
+                ((GameMode::TimeAttack(120), 3, Difficulty::Easy), 100),
+                ((GameMode::MoveLimit(50), 4, Difficulty::Hard), 700)
+            ])
+        };
+        save_records(&path, &records).unwrap();
+        let read = read_records(&path).unwrap();
+        assert_eq!((read.best_tile, read.daily_date, read.last_size), (1024, 20261014, 5));
+        assert_eq!(read.highscores, records.highscores);
+        assert_eq!(read.hardcore, records.hardcore);
+    }
+
+    #[test]
+    fn an_empty_save_holds_no_records_and_a_broken_one_is_corrupt() {
+        let path = temp_dir("empty").join("Highscore.bin");
+        fs::write(&path, []).unwrap();
+        let read = read_records(&path).unwrap();
+        assert_eq!((read.best_tile, read.daily_date, read.last_size), (0, 0, 0));
+        assert!(read.highscores.values().all(|&highscore| highscore == 0));
+        assert!(read.hardcore.is_empty());
+        fs::write(&path, [1, 2, 3]).unwrap();
+        assert!(matches!(read_records(&path), Err(GameError::CorruptSave(_))));
+    }
+
+    #[test]
+    fn an_interrupted_save_is_cleaned_up() {
+        let path = temp_dir("interrupted").join("Highscore.bin");
+        save_records(&path, &Records { best_tile: 64, ..Default::default() }).unwrap();
+        let temp = path.with_file_name("Highscore.bin.tmp");
+        fs::write(&temp, [0; 5]).unwrap();
+        assert_eq!(read_records(&path).unwrap().best_tile, 64);
+        assert!(!temp.exists());
+    }
+
+    #[test]
+    fn an_autosave_reads_back_as_the_same_game() {
+        let path = temp_dir("autosave").join("Autosave.json");
+        let game = SavedGame {
+            board: vec![vec![2, 0, 4], vec![0, 8, 0], vec![16, 0, 2]],
+            blocked: vec![1, 5],
+            score: 140,
+            moves: 23,
+            mode: GameMode::TimeAttack(90),
+            day: 0,
+            difficulty: Difficulty::Hard,
+            seed: u64::MAX,
+            played_secs: 41,
+            undos_used: 2,
+            swaps_used: 1,
+            continued: true,
+            set_up: false,
+            zen: false
+        };
+        save_game(&path, &game).unwrap();
+        assert_eq!(load_game(&path).unwrap(), game);
+        // the board alone still loads, and a board alone is no autosave
+        assert_eq!(load_state(&path).unwrap().values(), game.board);
+        save_state(&path, &game.board, 140).unwrap();
+        assert!(matches!(load_game(&path), Err(GameError::InvalidBoard(_))));
+    }
+}
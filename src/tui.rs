@@ -1,18 +1,23 @@
 use std::io::{self, stdout, Stdout};
 
-use crossterm::{execute, terminal::*};
+use crossterm::{
+    event::{DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture},
+    execute,
+    terminal::*
+};
 use ratatui::prelude::*;
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
 pub fn init () -> io::Result<Tui> {
-    execute!(stdout(), EnterAlternateScreen)?;
+    // focus changes pause the game, terminals that do not report them just never send any
+    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
     enable_raw_mode()?;
     Tui::new(CrosstermBackend::new(stdout()))
 }
 
 pub fn restore() -> io::Result<()> {
-    execute!(stdout(), LeaveAlternateScreen)?;
+    execute!(stdout(), DisableFocusChange, DisableMouseCapture, LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
 }
\ No newline at end of file